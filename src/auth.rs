@@ -1,24 +1,149 @@
+use argon2::Argon2;
+use argon2::password_hash::{
+    PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng,
+};
 use axum::{
     Json,
     body::Body,
     extract::State,
-    http::{Request, StatusCode},
+    http::{HeaderMap, Request, StatusCode, header},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use axum_extra::extract::cookie::{Cookie, CookieJar};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use tokio::sync::RwLock;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
 
 use crate::models::AppState;
 
 const USERS_FILE: &str = "users.json";
-const JWT_SECRET: &[u8] = b"secret_key_change_me_in_prod"; // In a real app, load from env
-const COOKIE_NAME: &str = "session";
+const INSECURE_DEFAULT_JWT_SECRET: &[u8] = b"secret_key_change_me_in_prod";
+/// Needs `pub(crate)` (not just private) because `routes::ui` checks login
+/// status straight off the cookie jar for its plain-HTML routes rather than
+/// going through `auth_middleware`'s `Claims` extension.
+pub(crate) const COOKIE_NAME: &str = "session";
+const REFRESH_COOKIE_NAME: &str = "refresh";
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+const REFRESH_TOKEN_TTL_DAYS: i64 = 7;
+/// How long a `TwoFactorClaims` token (minted by `login_handler` once the
+/// password checks out for a `totp_enabled` user) stays valid for
+/// `verify_totp_handler` to redeem.
+const TWO_FACTOR_TTL_MINUTES: i64 = 5;
+/// `issuer` shown alongside the account name in authenticator apps.
+const TOTP_ISSUER: &str = "sratim";
+/// Minimum length enforced by `register_handler`; the admin-facing
+/// `create_user_handler` has no such check since it's a trusted caller.
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Syntactic (not deliverability) email check: one `@`, a non-empty local
+/// part, and a domain part containing at least one `.`.
+fn is_valid_email(email: &str) -> bool {
+    static EMAIL_RE: OnceLock<Regex> = OnceLock::new();
+    let email_re = EMAIL_RE.get_or_init(|| Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@]+$").unwrap());
+    email_re.is_match(email)
+}
+
+/// Runtime-configurable pieces of the auth/cookie setup, loaded once in
+/// `AuthState::new` from environment variables. Letting operators set these
+/// without a rebuild avoids shipping a single compile-time JWT secret and a
+/// cookie policy that's unsafe behind plain HTTP.
+#[derive(Clone)]
+pub struct AuthConfig {
+    jwt_secret: Vec<u8>,
+    access_token_ttl_minutes: i64,
+    cookie_domain: Option<String>,
+    /// When set, cookies are marked `Secure` and `SameSite=Strict` instead
+    /// of the insecure-local defaults (`SameSite=Lax`, no `Secure`).
+    secure_cookies: bool,
+}
+
+impl AuthConfig {
+    /// `tls_active` is `tls::tls_active(&config)` -- when the server is
+    /// bound over rustls, cookies are forced `Secure` even if an operator
+    /// never set `SRATIM_SECURE_COOKIES`, since the historical plaintext-
+    /// local default would otherwise send the session cookie over HTTPS
+    /// with `Secure` unset for no reason.
+    fn from_env(tls_active: bool) -> Self {
+        let jwt_secret = match std::env::var("SRATIM_JWT_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret.into_bytes(),
+            _ => {
+                eprintln!(
+                    "[auth] WARNING: SRATIM_JWT_SECRET is not set; signing tokens with an \
+                     insecure, publicly-known default secret. Set SRATIM_JWT_SECRET before \
+                     deploying to production."
+                );
+                INSECURE_DEFAULT_JWT_SECRET.to_vec()
+            }
+        };
+
+        let access_token_ttl_minutes = std::env::var("SRATIM_ACCESS_TOKEN_TTL_MINUTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(ACCESS_TOKEN_TTL_MINUTES);
+
+        let cookie_domain = std::env::var("SRATIM_COOKIE_DOMAIN").ok();
+
+        let secure_cookies = tls_active
+            || std::env::var("SRATIM_SECURE_COOKIES")
+                .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+                .unwrap_or(false);
+
+        Self {
+            jwt_secret,
+            access_token_ttl_minutes,
+            cookie_domain,
+            secure_cookies,
+        }
+    }
+}
+
+/// Argon2id tuning, in `argon2::Params::new` order (memory KiB, iterations,
+/// parallelism). A constant so the cost can be retuned for new hardware
+/// without touching the hashing/verification call sites.
+const ARGON2_PARAMS: (u32, u32, u32) = (19_456, 2, 1);
+
+fn argon2() -> Argon2<'static> {
+    let (m_cost, t_cost, p_cost) = ARGON2_PARAMS;
+    Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        argon2::Params::new(m_cost, t_cost, p_cost, None).expect("valid Argon2 params"),
+    )
+}
+
+fn hash_password(password: &str) -> Result<String, AuthError> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AuthError::HashError(e.to_string()))
+}
+
+/// Verifies `password` against `stored_hash`, supporting both the current
+/// Argon2id PHC format (`$argon2id$...`) and legacy bcrypt hashes
+/// (`$2a$`/`$2b$`/`$2y$`) so existing `users.json` entries keep working.
+/// Callers that successfully verify against a bcrypt hash are expected to
+/// rehash the password with `hash_password` and persist it.
+fn verify_password(password: &str, stored_hash: &str) -> bool {
+    if stored_hash.starts_with("$2") {
+        bcrypt::verify(password, stored_hash).unwrap_or(false)
+    } else {
+        PasswordHash::new(stored_hash)
+            .map(|parsed| {
+                argon2()
+                    .verify_password(password.as_bytes(), &parsed)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct User {
@@ -26,6 +151,24 @@ pub struct User {
     pub password_hash: String,
     #[serde(default)]
     pub is_admin: bool,
+    /// `jti`s of refresh tokens minted for this user that haven't been
+    /// revoked by `logout_handler` or a password change. Checked by
+    /// `refresh_handler` so a stolen-but-revoked refresh token can't be used
+    /// to mint new access tokens.
+    #[serde(default)]
+    pub valid_refresh_ids: HashSet<String>,
+    /// Base32-encoded TOTP secret, set by `enroll_totp_handler` once the
+    /// user scans the provisioning URI. Present but unconfirmed secrets
+    /// (`totp_enabled == false`) don't gate login -- only `confirm_totp_handler`
+    /// flipping `totp_enabled` does.
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    #[serde(default)]
+    pub totp_enabled: bool,
+    /// Only set for users created through `register_handler`; admin-created
+    /// and bootstrap users leave this `None`.
+    #[serde(default)]
+    pub email: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +177,10 @@ pub struct LoginPayload {
     pub password: String,
 }
 
+/// Short-lived access token claims, attached to the request by
+/// `auth_middleware` and read by protected handlers. Kept deliberately
+/// small (no `jti`) since access tokens aren't individually revocable --
+/// that's what the separate refresh token is for.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
     pub sub: String,
@@ -41,16 +188,313 @@ pub struct Claims {
     pub exp: usize,
 }
 
+/// Long-lived refresh token claims, stored only in `REFRESH_COOKIE_NAME`.
+/// `jti` is checked against `User::valid_refresh_ids` so a single refresh
+/// token can be revoked (logout, password change) without invalidating
+/// every other session for the user.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct RefreshClaims {
+    sub: String,
+    jti: String,
+    exp: usize,
+}
+
+/// Short-lived claim proving a user supplied the right password but hasn't
+/// yet completed the TOTP challenge. Minted by `login_handler` in place of
+/// the real session when `User::totp_enabled` is set, and redeemed once by
+/// `verify_totp_handler`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct TwoFactorClaims {
+    sub: String,
+    exp: usize,
+}
+
+fn mint_two_factor_token(username: &str, cfg: &AuthConfig) -> String {
+    let expiration = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::minutes(TWO_FACTOR_TTL_MINUTES))
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = TwoFactorClaims {
+        sub: username.to_string(),
+        exp: expiration as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&cfg.jwt_secret),
+    )
+    .unwrap()
+}
+
+/// Builds a RFC 6238 validator for a base32-encoded TOTP `secret`: SHA-1,
+/// 6 digits, a 30-second step, and a `skew` of 1 so codes from the current
+/// step and the adjacent ones either side are accepted, tolerating clock
+/// drift between the server and an authenticator app.
+fn build_totp(secret: &str, username: &str) -> Result<TOTP, AuthError> {
+    let secret_bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AuthError::InvalidTotp)?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret_bytes,
+        Some(TOTP_ISSUER.to_string()),
+        username.to_string(),
+    )
+    .map_err(|_| AuthError::InvalidTotp)
+}
+
+fn mint_access_token(user: &User, cfg: &AuthConfig) -> String {
+    let expiration = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::minutes(cfg.access_token_ttl_minutes))
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = Claims {
+        sub: user.username.clone(),
+        is_admin: user.is_admin,
+        exp: expiration as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&cfg.jwt_secret),
+    )
+    .unwrap()
+}
+
+/// Mints a fresh refresh token for `user`, recording its `jti` as valid so
+/// `refresh_handler` will later accept it. Caller is responsible for
+/// persisting `user` afterwards.
+fn mint_refresh_token(user: &mut User, cfg: &AuthConfig) -> String {
+    let jti = Uuid::new_v4().to_string();
+    let expiration = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS))
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = RefreshClaims {
+        sub: user.username.clone(),
+        jti: jti.clone(),
+        exp: expiration as usize,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&cfg.jwt_secret),
+    )
+    .unwrap();
+
+    user.valid_refresh_ids.insert(jti);
+    token
+}
+
+/// Claims for `routes::feed`'s per-library RSS link, carried as a query
+/// param (`?token=`) rather than the `session` cookie since podcast/video
+/// clients fetching the feed and its enclosures can't be expected to send
+/// one. Scoped to a single `library_id` and given a long `exp` (unlike
+/// `Claims`) since re-issuing it would mean the user re-pasting the feed
+/// URL into their client.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedClaims {
+    pub library_id: String,
+    pub exp: usize,
+}
+
+const FEED_TOKEN_TTL_DAYS: i64 = 365;
+
+/// Signs a `FeedClaims` for `library_id`, for `routes::feed::feed_handler`
+/// to hand out and later verify via `verify_feed_token`.
+pub fn mint_feed_token(library_id: &str, cfg: &AuthConfig) -> String {
+    let expiration = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::days(FEED_TOKEN_TTL_DAYS))
+        .expect("valid timestamp")
+        .timestamp();
+
+    let claims = FeedClaims {
+        library_id: library_id.to_string(),
+        exp: expiration as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&cfg.jwt_secret),
+    )
+    .unwrap()
+}
+
+/// Validates `token` and checks it was minted for `library_id` specifically
+/// -- a feed token for one library shouldn't unlock another.
+pub fn verify_feed_token(token: &str, library_id: &str, cfg: &AuthConfig) -> bool {
+    let validation = Validation::default();
+    match decode::<FeedClaims>(
+        token,
+        &DecodingKey::from_secret(&cfg.jwt_secret),
+        &validation,
+    ) {
+        Ok(data) => data.claims.library_id == library_id,
+        Err(_) => false,
+    }
+}
+
+/// Extracts and validates an access token from either the `session` cookie
+/// or an `Authorization: Bearer <token>` header (checked in that order), so
+/// browser sessions and non-browser API clients are authenticated
+/// identically. Used by `auth_middleware`, `me_handler`, and
+/// `change_password_handler`.
+fn extract_claims(
+    jar: &CookieJar,
+    headers: &HeaderMap,
+    cfg: &AuthConfig,
+) -> Result<Claims, AuthError> {
+    let token = jar
+        .get(COOKIE_NAME)
+        .map(|cookie| cookie.value().to_string())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(|value| value.to_string())
+        })
+        .ok_or(AuthError::MissingToken)?;
+
+    let validation = Validation::default();
+    decode::<Claims>(
+        &token,
+        &DecodingKey::from_secret(&cfg.jwt_secret),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .map_err(|_| AuthError::InvalidToken)
+}
+
+/// Like `extract_claims`, but for `routes::ui`'s plain-HTML handlers, which
+/// only ever have a cookie (no `Authorization` header fallback to check) and
+/// want `None` rather than a JSON `AuthError` on failure so they can redirect
+/// to the login page instead.
+pub(crate) fn verify_session_cookie(token: &str, cfg: &AuthConfig) -> Option<Claims> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(&cfg.jwt_secret),
+        &Validation::default(),
+    )
+    .ok()
+    .map(|data| data.claims)
+}
+
+/// Uniform, machine-parseable error shape for every handler in this module,
+/// replacing the ad-hoc `(StatusCode, &str)` tuples handlers used to return
+/// directly.
+#[derive(Debug)]
+pub enum AuthError {
+    MissingCredentials,
+    InvalidCredentials,
+    MissingToken,
+    InvalidToken,
+    MissingUser,
+    UserExists,
+    Forbidden(&'static str),
+    InvalidTotp,
+    InvalidEmail,
+    WeakPassword,
+    HashError(String),
+    Io(std::io::Error),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, message) = match &self {
+            AuthError::MissingCredentials => {
+                (StatusCode::BAD_REQUEST, "Missing credentials".to_string())
+            }
+            AuthError::InvalidCredentials => {
+                (StatusCode::UNAUTHORIZED, "Invalid credentials".to_string())
+            }
+            AuthError::MissingToken => (StatusCode::UNAUTHORIZED, "Missing token".to_string()),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".to_string()),
+            AuthError::MissingUser => (StatusCode::NOT_FOUND, "User not found".to_string()),
+            AuthError::UserExists => (StatusCode::CONFLICT, "User already exists".to_string()),
+            AuthError::Forbidden(message) => (StatusCode::FORBIDDEN, message.to_string()),
+            AuthError::InvalidTotp => (
+                StatusCode::UNAUTHORIZED,
+                "Invalid authentication code".to_string(),
+            ),
+            AuthError::InvalidEmail => {
+                (StatusCode::BAD_REQUEST, "Invalid email address".to_string())
+            }
+            AuthError::WeakPassword => (
+                StatusCode::BAD_REQUEST,
+                format!("Password must be at least {} characters", MIN_PASSWORD_LEN),
+            ),
+            AuthError::HashError(message) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to hash password: {}", message),
+            ),
+            AuthError::Io(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to persist users: {}", e),
+            ),
+        };
+
+        (
+            status,
+            Json(serde_json::json!({ "status": status.as_str(), "message": message })),
+        )
+            .into_response()
+    }
+}
+
+/// Builds a cookie for `name`/`value` using `cfg`'s hardening settings.
+/// Locally (no `SRATIM_SECURE_COOKIES`) this falls back to the historical
+/// insecure defaults -- `SameSite=Lax`, no `Secure` flag, no domain -- so
+/// plain-HTTP local development keeps working unchanged.
+fn build_cookie(
+    name: &'static str,
+    value: String,
+    max_age_secs: i64,
+    cfg: &AuthConfig,
+) -> Cookie<'static> {
+    let mut builder = Cookie::build((name, value))
+        .path("/")
+        .http_only(true)
+        .same_site(if cfg.secure_cookies {
+            axum_extra::extract::cookie::SameSite::Strict
+        } else {
+            axum_extra::extract::cookie::SameSite::Lax
+        })
+        .secure(cfg.secure_cookies)
+        .max_age(time::Duration::seconds(max_age_secs));
+
+    if let Some(domain) = cfg.cookie_domain.clone() {
+        builder = builder.domain(domain);
+    }
+
+    builder.build()
+}
+
 #[derive(Clone)]
 pub struct AuthState {
     pub users: Arc<RwLock<HashMap<String, User>>>,
+    pub config: AuthConfig,
 }
 
 impl AuthState {
-    pub async fn new() -> Self {
+    /// `tls_active` is forwarded to `AuthConfig::from_env` to force `Secure`
+    /// cookies on whenever the caller intends to bind over rustls -- see
+    /// `tls::tls_active`.
+    pub async fn new(tls_active: bool) -> Self {
         let users = Arc::new(RwLock::new(HashMap::new()));
         let state = Self {
             users: users.clone(),
+            config: AuthConfig::from_env(tls_active),
         };
         state.load_or_create_default().await;
         state
@@ -79,6 +523,10 @@ impl AuthState {
             username: "admin".to_string(),
             password_hash: hash,
             is_admin: true,
+            valid_refresh_ids: HashSet::new(),
+            totp_secret: None,
+            totp_enabled: false,
+            email: None,
         };
 
         let mut map = self.users.write().await;
@@ -102,94 +550,246 @@ pub async fn login_handler(
     State(state): State<AppState>,
     jar: CookieJar,
     Json(payload): Json<LoginPayload>,
-) -> impl IntoResponse {
-    let auth_map = state.auth.users.read().await;
+) -> Result<Response, AuthError> {
+    let mut users = state.auth.users.write().await;
 
-    if let Some(user) = auth_map.get(&payload.username)
-        && bcrypt::verify(&payload.password, &user.password_hash).unwrap_or(false)
-    {
-        // Create JWT
-        let expiration = chrono::Utc::now()
-            .checked_add_signed(chrono::Duration::hours(24))
-            .expect("valid timestamp")
-            .timestamp();
-
-        let claims = Claims {
-            sub: user.username.clone(),
-            is_admin: user.is_admin,
-            exp: expiration as usize,
-        };
+    let user = users
+        .get_mut(&payload.username)
+        .filter(|user| verify_password(&payload.password, &user.password_hash))
+        .ok_or(AuthError::InvalidCredentials)?;
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(JWT_SECRET),
-        )
-        .unwrap();
+    // The password just verified against a legacy bcrypt hash; upgrade it to
+    // Argon2id now that we have the plaintext, so the store migrates itself
+    // over time with no forced password reset.
+    if user.password_hash.starts_with("$2")
+        && let Ok(upgraded) = hash_password(&payload.password)
+    {
+        user.password_hash = upgraded;
+    }
 
-        let cookie = Cookie::build((COOKIE_NAME, token))
-            .path("/")
-            .http_only(true)
-            .same_site(axum_extra::extract::cookie::SameSite::Lax)
-            .build();
+    let cfg = state.auth.config.clone();
 
-        let mut response = Json("Login successful").into_response();
-        let cookie_res = jar.add(cookie).into_response();
-        response.headers_mut().extend(cookie_res.headers().clone());
-        return response;
+    // A correct password isn't enough for a 2FA-enrolled user: hand back a
+    // short-lived pending token instead of the real session, and make them
+    // redeem it with a TOTP code via `verify_totp_handler`.
+    if user.totp_enabled {
+        let two_factor_token = mint_two_factor_token(&user.username, &cfg);
+        return Ok(Json(serde_json::json!({
+            "status": "2fa_required",
+            "two_factor_token": two_factor_token,
+        }))
+        .into_response());
     }
 
-    (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response()
-}
+    let access_token = mint_access_token(user, &cfg);
+    let refresh_token = mint_refresh_token(user, &cfg);
 
-pub async fn auth_middleware(jar: CookieJar, mut req: Request<Body>, next: Next) -> Response {
-    if let Some(token) = jar.get(COOKIE_NAME) {
-        let validation = Validation::default();
-        if let Ok(data) = decode::<Claims>(
-            token.value(),
-            &DecodingKey::from_secret(JWT_SECRET),
-            &validation,
-        ) {
-            req.extensions_mut().insert(data.claims);
-            return next.run(req).await;
-        }
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
     }
 
-    // Cookie missing or invalid
-    StatusCode::UNAUTHORIZED.into_response()
+    let session_cookie = build_cookie(
+        COOKIE_NAME,
+        access_token,
+        cfg.access_token_ttl_minutes * 60,
+        &cfg,
+    );
+    let refresh_cookie = build_cookie(
+        REFRESH_COOKIE_NAME,
+        refresh_token,
+        REFRESH_TOKEN_TTL_DAYS * 24 * 60 * 60,
+        &cfg,
+    );
+
+    let mut response = Json("Login successful").into_response();
+    let cookie_res = jar.add(session_cookie).add(refresh_cookie).into_response();
+    response.headers_mut().extend(cookie_res.headers().clone());
+    Ok(response)
 }
 
-pub async fn logout_handler(jar: CookieJar) -> impl IntoResponse {
-    let cookie = Cookie::build((COOKIE_NAME, ""))
-        .path("/")
-        .http_only(true)
-        .same_site(axum_extra::extract::cookie::SameSite::Lax)
-        .max_age(time::Duration::seconds(0))
-        .build();
+/// Issues a fresh access token from a still-valid refresh token, so clients
+/// can stay logged in past the short access-token lifetime without
+/// re-submitting credentials.
+pub async fn refresh_handler(State(state): State<AppState>, jar: CookieJar) -> impl IntoResponse {
+    let Some(token) = jar.get(REFRESH_COOKIE_NAME) else {
+        return (StatusCode::UNAUTHORIZED, "Missing refresh token").into_response();
+    };
 
-    let mut response = Json("Logged out").into_response();
+    let cfg = state.auth.config.clone();
+    let validation = Validation::default();
+    let Ok(data) = decode::<RefreshClaims>(
+        token.value(),
+        &DecodingKey::from_secret(&cfg.jwt_secret),
+        &validation,
+    ) else {
+        return (StatusCode::UNAUTHORIZED, "Invalid refresh token").into_response();
+    };
+
+    let mut users = state.auth.users.write().await;
+    let Some(user) = users.get_mut(&data.claims.sub) else {
+        return (StatusCode::UNAUTHORIZED, "Invalid refresh token").into_response();
+    };
+
+    if !user.valid_refresh_ids.contains(&data.claims.jti) {
+        return (StatusCode::UNAUTHORIZED, "Refresh token revoked").into_response();
+    }
+
+    let access_token = mint_access_token(user, &cfg);
+    let cookie = build_cookie(
+        COOKIE_NAME,
+        access_token,
+        cfg.access_token_ttl_minutes * 60,
+        &cfg,
+    );
+
+    let mut response = Json("Token refreshed").into_response();
     let cookie_res = jar.add(cookie).into_response();
     response.headers_mut().extend(cookie_res.headers().clone());
     response
 }
 
-pub async fn me_handler(jar: CookieJar) -> impl IntoResponse {
-    if let Some(token) = jar.get(COOKIE_NAME) {
+#[derive(Debug, Deserialize)]
+pub struct VerifyTotpPayload {
+    pub two_factor_token: String,
+    pub code: String,
+}
+
+/// Completes the two-step login flow `login_handler` starts for a
+/// `totp_enabled` user: exchanges the short-lived 2FA-pending token plus a
+/// 6-digit TOTP code for the real session and refresh cookies.
+pub async fn verify_totp_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(payload): Json<VerifyTotpPayload>,
+) -> Result<Response, AuthError> {
+    let cfg = state.auth.config.clone();
+    let validation = Validation::default();
+    let data = decode::<TwoFactorClaims>(
+        &payload.two_factor_token,
+        &DecodingKey::from_secret(&cfg.jwt_secret),
+        &validation,
+    )
+    .map_err(|_| AuthError::InvalidToken)?;
+
+    let mut users = state.auth.users.write().await;
+    let user = users
+        .get_mut(&data.claims.sub)
+        .ok_or(AuthError::MissingUser)?;
+    let secret = user.totp_secret.clone().ok_or(AuthError::InvalidTotp)?;
+
+    let totp = build_totp(&secret, &user.username)?;
+    if !totp.check_current(&payload.code).unwrap_or(false) {
+        return Err(AuthError::InvalidTotp);
+    }
+
+    let access_token = mint_access_token(user, &cfg);
+    let refresh_token = mint_refresh_token(user, &cfg);
+
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
+    }
+
+    let session_cookie = build_cookie(
+        COOKIE_NAME,
+        access_token,
+        cfg.access_token_ttl_minutes * 60,
+        &cfg,
+    );
+    let refresh_cookie = build_cookie(
+        REFRESH_COOKIE_NAME,
+        refresh_token,
+        REFRESH_TOKEN_TTL_DAYS * 24 * 60 * 60,
+        &cfg,
+    );
+
+    let mut response = Json("Login successful").into_response();
+    let cookie_res = jar.add(session_cookie).add(refresh_cookie).into_response();
+    response.headers_mut().extend(cookie_res.headers().clone());
+    Ok(response)
+}
+
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    match extract_claims(&jar, req.headers(), &state.auth.config) {
+        Ok(claims) => {
+            req.extensions_mut().insert(claims);
+            next.run(req).await
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Rejects requests whose `Claims` (inserted by `auth_middleware`, which
+/// must run first) aren't an admin's. Intended to be layered over the
+/// user-management routes (`list_users_handler`, `create_user_handler`,
+/// `delete_user_handler`, `admin_change_password_handler`, `reset_totp_handler`)
+/// as a nested `middleware::from_fn` layer so only `auth_middleware` has to
+/// run on every other route.
+pub async fn require_admin(req: Request<Body>, next: Next) -> Response {
+    match req.extensions().get::<Claims>() {
+        Some(claims) if claims.is_admin => next.run(req).await,
+        Some(_) => AuthError::Forbidden("Admin privileges required").into_response(),
+        None => AuthError::MissingToken.into_response(),
+    }
+}
+
+pub async fn logout_handler(State(state): State<AppState>, jar: CookieJar) -> impl IntoResponse {
+    let cfg = state.auth.config.clone();
+    if let Some(token) = jar.get(REFRESH_COOKIE_NAME) {
         let validation = Validation::default();
-        if let Ok(data) = decode::<Claims>(
+        if let Ok(data) = decode::<RefreshClaims>(
             token.value(),
-            &DecodingKey::from_secret(JWT_SECRET),
+            &DecodingKey::from_secret(&cfg.jwt_secret),
             &validation,
         ) {
-            return Json(User {
-                username: data.claims.sub,
-                password_hash: "".to_string(),
-                is_admin: data.claims.is_admin,
-            })
-            .into_response();
+            let mut users = state.auth.users.write().await;
+            if let Some(user) = users.get_mut(&data.claims.sub) {
+                user.valid_refresh_ids.remove(&data.claims.jti);
+                let all_users: Vec<User> = users.values().cloned().collect();
+                if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+                    let _ = tokio::fs::write(USERS_FILE, content).await;
+                }
+            }
         }
     }
-    StatusCode::UNAUTHORIZED.into_response()
+
+    let session_cookie = build_cookie(COOKIE_NAME, String::new(), 0, &cfg);
+    let refresh_cookie = build_cookie(REFRESH_COOKIE_NAME, String::new(), 0, &cfg);
+
+    let mut response = Json("Logged out").into_response();
+    let cookie_res = jar.add(session_cookie).add(refresh_cookie).into_response();
+    response.headers_mut().extend(cookie_res.headers().clone());
+    response
+}
+
+pub async fn me_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    match extract_claims(&jar, &headers, &state.auth.config) {
+        Ok(claims) => Json(User {
+            username: claims.sub,
+            password_hash: "".to_string(),
+            is_admin: claims.is_admin,
+            valid_refresh_ids: HashSet::new(),
+            totp_secret: None,
+            totp_enabled: false,
+            email: None,
+        })
+        .into_response(),
+        Err(err) => err.into_response(),
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -201,49 +801,153 @@ pub struct ChangePasswordPayload {
 pub async fn change_password_handler(
     State(state): State<AppState>,
     jar: CookieJar,
+    headers: HeaderMap,
     Json(payload): Json<ChangePasswordPayload>,
-) -> impl IntoResponse {
-    let user_name = if let Some(token) = jar.get(COOKIE_NAME) {
-        let validation = Validation::default();
-        if let Ok(data) = decode::<Claims>(
-            token.value(),
-            &DecodingKey::from_secret(JWT_SECRET),
-            &validation,
-        ) {
-            data.claims.sub
-        } else {
-            return (StatusCode::UNAUTHORIZED, "Invalid token").into_response();
-        }
-    } else {
-        return (StatusCode::UNAUTHORIZED, "Not logged in").into_response();
-    };
+) -> Result<Json<&'static str>, AuthError> {
+    let user_name = extract_claims(&jar, &headers, &state.auth.config)?.sub;
 
     let mut users = state.auth.users.write().await;
+    let user = users.get_mut(&user_name).ok_or(AuthError::MissingUser)?;
 
-    if let Some(user) = users.get_mut(&user_name) {
-        if !bcrypt::verify(&payload.current_password, &user.password_hash).unwrap_or(false) {
-            return (StatusCode::UNAUTHORIZED, "Invalid current password").into_response();
-        }
+    if !verify_password(&payload.current_password, &user.password_hash) {
+        return Err(AuthError::InvalidCredentials);
+    }
 
-        match bcrypt::hash(&payload.new_password, bcrypt::DEFAULT_COST) {
-            Ok(hash) => {
-                user.password_hash = hash;
-                // Manual save logic since we hold the write lock
-                let all_users: Vec<User> = users.values().cloned().collect();
-                if let Ok(content) = serde_json::to_string_pretty(&all_users) {
-                    let _ = tokio::fs::write(USERS_FILE, content).await;
-                }
+    let hash = hash_password(&payload.new_password)?;
+    user.password_hash = hash;
+    // A password change invalidates every outstanding refresh token,
+    // forcing other sessions to log in again.
+    user.valid_refresh_ids.clear();
 
-                return Json("Password changed successfully").into_response();
-            }
-            Err(_) => {
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password")
-                    .into_response();
-            }
-        }
+    // Manual save logic since we hold the write lock
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
     }
 
-    (StatusCode::UNAUTHORIZED, "User not found").into_response()
+    Ok(Json("Password changed successfully"))
+}
+
+#[derive(Serialize)]
+pub struct EnrollTotpResponse {
+    pub otpauth_url: String,
+}
+
+/// Starts TOTP enrollment for the authenticated user: generates a new
+/// base32 secret, stores it on `User::totp_secret`, and returns its
+/// `otpauth://` provisioning URI for a QR code / authenticator app.
+/// `totp_enabled` stays false until `confirm_totp_handler` proves the user
+/// captured the secret correctly.
+pub async fn enroll_totp_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+) -> Result<Json<EnrollTotpResponse>, AuthError> {
+    let user_name = extract_claims(&jar, &headers, &state.auth.config)?.sub;
+
+    let mut users = state.auth.users.write().await;
+    let user = users.get_mut(&user_name).ok_or(AuthError::MissingUser)?;
+
+    let secret_base32 = Secret::generate_secret().to_encoded().to_string();
+    let totp = build_totp(&secret_base32, &user_name)?;
+    let otpauth_url = totp.get_url();
+
+    user.totp_secret = Some(secret_base32);
+    user.totp_enabled = false;
+
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
+    }
+
+    Ok(Json(EnrollTotpResponse { otpauth_url }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmTotpPayload {
+    pub code: String,
+}
+
+/// Flips `User::totp_enabled` once the user proves, with a valid code, that
+/// they successfully captured the secret `enroll_totp_handler` generated.
+pub async fn confirm_totp_handler(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    Json(payload): Json<ConfirmTotpPayload>,
+) -> Result<Json<&'static str>, AuthError> {
+    let user_name = extract_claims(&jar, &headers, &state.auth.config)?.sub;
+
+    let mut users = state.auth.users.write().await;
+    let user = users.get_mut(&user_name).ok_or(AuthError::MissingUser)?;
+    let secret = user.totp_secret.clone().ok_or(AuthError::InvalidTotp)?;
+
+    let totp = build_totp(&secret, &user_name)?;
+    if !totp.check_current(&payload.code).unwrap_or(false) {
+        return Err(AuthError::InvalidTotp);
+    }
+
+    user.totp_enabled = true;
+
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
+    }
+
+    Ok(Json("Two-factor authentication enabled"))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RegisterPayload {
+    pub username: String,
+    pub email: String,
+    pub password: String,
+}
+
+/// Public self-service sign-up, independent of the admin-only
+/// `create_user_handler`. Registered users are always non-admin.
+pub async fn register_handler(
+    State(state): State<AppState>,
+    Json(payload): Json<RegisterPayload>,
+) -> Result<Json<&'static str>, AuthError> {
+    if !is_valid_email(&payload.email) {
+        return Err(AuthError::InvalidEmail);
+    }
+    if payload.password.len() < MIN_PASSWORD_LEN {
+        return Err(AuthError::WeakPassword);
+    }
+
+    let mut users = state.auth.users.write().await;
+    if users.contains_key(&payload.username) {
+        return Err(AuthError::UserExists);
+    }
+
+    let hash = hash_password(&payload.password)?;
+    let new_user = User {
+        username: payload.username.clone(),
+        password_hash: hash,
+        is_admin: false,
+        valid_refresh_ids: HashSet::new(),
+        totp_secret: None,
+        totp_enabled: false,
+        email: Some(payload.email),
+    };
+    users.insert(payload.username.clone(), new_user);
+
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
+    }
+
+    Ok(Json("Registration successful"))
 }
 
 // --- User Management API ---
@@ -273,82 +977,111 @@ pub async fn list_users_handler(State(state): State<AppState>) -> impl IntoRespo
 pub async fn create_user_handler(
     State(state): State<AppState>,
     Json(payload): Json<ManageUserPayload>,
-) -> impl IntoResponse {
+) -> Result<Json<&'static str>, AuthError> {
     let mut users = state.auth.users.write().await;
 
     if users.contains_key(&payload.username) {
-        return (StatusCode::CONFLICT, "User already exists").into_response();
+        return Err(AuthError::UserExists);
     }
 
     let password = payload.password.unwrap_or_else(|| "123456".to_string()); // Default password if not provided
+    let hash = hash_password(&password)?;
 
-    match bcrypt::hash(&password, bcrypt::DEFAULT_COST) {
-        Ok(hash) => {
-            let new_user = User {
-                username: payload.username.clone(),
-                password_hash: hash,
-                is_admin: false,
-            };
-            users.insert(payload.username.clone(), new_user);
-
-            // Manual save logic
-            let all_users: Vec<User> = users.values().cloned().collect();
-            if let Ok(content) = serde_json::to_string_pretty(&all_users) {
-                let _ = tokio::fs::write(USERS_FILE, content).await;
-            }
+    let new_user = User {
+        username: payload.username.clone(),
+        password_hash: hash,
+        is_admin: false,
+        valid_refresh_ids: HashSet::new(),
+        totp_secret: None,
+        totp_enabled: false,
+        email: None,
+    };
+    users.insert(payload.username.clone(), new_user);
 
-            Json("User created").into_response()
-        }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password").into_response(),
+    // Manual save logic
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
     }
+
+    Ok(Json("User created"))
 }
 
 pub async fn delete_user_handler(
     State(state): State<AppState>,
+    axum::Extension(acting): axum::Extension<Claims>,
     axum::extract::Path(username): axum::extract::Path<String>,
-) -> impl IntoResponse {
+) -> Result<Json<&'static str>, AuthError> {
+    if username == acting.sub {
+        return Err(AuthError::Forbidden("Cannot delete your own account"));
+    }
+
     let mut users = state.auth.users.write().await;
+    let target = users.get(&username).ok_or(AuthError::MissingUser)?;
+    if target.is_admin && users.values().filter(|u| u.is_admin).count() <= 1 {
+        return Err(AuthError::Forbidden(
+            "Cannot delete the last remaining admin",
+        ));
+    }
 
-    if users.remove(&username).is_some() {
-        // Manual save logic
-        let all_users: Vec<User> = users.values().cloned().collect();
-        if let Ok(content) = serde_json::to_string_pretty(&all_users) {
-            let _ = tokio::fs::write(USERS_FILE, content).await;
-        }
-        return Json("User deleted").into_response();
+    users.remove(&username);
+
+    // Manual save logic
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
     }
 
-    (StatusCode::NOT_FOUND, "User not found").into_response()
+    Ok(Json("User deleted"))
 }
 
 pub async fn admin_change_password_handler(
     State(state): State<AppState>,
     axum::extract::Path(username): axum::extract::Path<String>,
     Json(payload): Json<ManageUserPayload>,
-) -> impl IntoResponse {
+) -> Result<Json<&'static str>, AuthError> {
     let mut users = state.auth.users.write().await;
+    let user = users.get_mut(&username).ok_or(AuthError::MissingUser)?;
+    let new_pass = payload.password.ok_or(AuthError::MissingCredentials)?;
 
-    if let Some(user) = users.get_mut(&username) {
-        if let Some(new_pass) = payload.password {
-            match bcrypt::hash(&new_pass, bcrypt::DEFAULT_COST) {
-                Ok(hash) => {
-                    user.password_hash = hash;
-                    // Manual save logic
-                    let all_users: Vec<User> = users.values().cloned().collect();
-                    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
-                        let _ = tokio::fs::write(USERS_FILE, content).await;
-                    }
-                    return Json("Password updated").into_response();
-                }
-                Err(_) => {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password")
-                        .into_response();
-                }
-            }
-        } else {
-            return (StatusCode::BAD_REQUEST, "No password provided").into_response();
-        }
+    let hash = hash_password(&new_pass)?;
+    user.password_hash = hash;
+
+    // Manual save logic
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
+    }
+
+    Ok(Json("Password updated"))
+}
+
+/// Admin-only escape hatch mirroring `admin_change_password_handler`: clears
+/// a locked-out user's TOTP secret and disables 2FA so they can log in with
+/// just their password again and re-enroll.
+pub async fn reset_totp_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(username): axum::extract::Path<String>,
+) -> Result<Json<&'static str>, AuthError> {
+    let mut users = state.auth.users.write().await;
+    let user = users.get_mut(&username).ok_or(AuthError::MissingUser)?;
+
+    user.totp_secret = None;
+    user.totp_enabled = false;
+
+    // Manual save logic
+    let all_users: Vec<User> = users.values().cloned().collect();
+    if let Ok(content) = serde_json::to_string_pretty(&all_users) {
+        tokio::fs::write(USERS_FILE, content)
+            .await
+            .map_err(AuthError::Io)?;
     }
 
-    (StatusCode::NOT_FOUND, "User not found").into_response()
+    Ok(Json("Two-factor authentication reset"))
 }
@@ -0,0 +1,168 @@
+//! Shared BlurHash encoder, used by `metadata` for downloaded TMDB posters
+//! so the DCT/base83 logic lives in one place. Images are sampled via ffmpeg rather than an image-decoding
+//! crate, matching the rest of the media pipeline's "shell out to ffmpeg"
+//! style.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+pub const DEFAULT_SAMPLE_WIDTH: usize = 32;
+pub const DEFAULT_SAMPLE_HEIGHT: usize = 18;
+pub const DEFAULT_COMPONENTS_X: usize = 4;
+pub const DEFAULT_COMPONENTS_Y: usize = 3;
+
+const BASE83_CHARS: &[u8] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz#$%*+,-.:;=?@[]^_{|}~";
+
+/// Decodes a small raw-RGB render of `image_path` via ffmpeg (no
+/// image-decoding crate needed) and encodes it as a BlurHash string.
+pub async fn encode_image_file(
+    image_path: &Path,
+    width: usize,
+    height: usize,
+    components_x: usize,
+    components_y: usize,
+) -> Result<String> {
+    let output = tokio::process::Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(image_path)
+        .args([
+            "-vf",
+            &format!("scale={}:{}", width, height),
+            "-frames:v",
+            "1",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgb24",
+            "pipe:1",
+        ])
+        .output()
+        .await
+        .context("Failed to spawn ffmpeg for BlurHash sampling")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "ffmpeg raw sampling failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let expected_len = width * height * 3;
+    if output.stdout.len() != expected_len {
+        anyhow::bail!(
+            "unexpected raw frame size: got {} bytes, expected {}",
+            output.stdout.len(),
+            expected_len
+        );
+    }
+
+    Ok(encode_blurhash(
+        &output.stdout,
+        width,
+        height,
+        components_x,
+        components_y,
+    ))
+}
+
+fn srgb_to_linear(c: u8) -> f64 {
+    let v = c as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f64) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let s = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (s * 255.0 + 0.5).floor().clamp(0.0, 255.0) as u8
+}
+
+fn encode_base83(mut value: u32, length: usize) -> String {
+    let mut chars = vec![0u8; length];
+    for i in (0..length).rev() {
+        chars[i] = BASE83_CHARS[(value % 83) as usize];
+        value /= 83;
+    }
+    String::from_utf8(chars).expect("base83 alphabet is ASCII")
+}
+
+/// Sums `cos(pi*i*x/w) * cos(pi*j*y/h) * linearColor` over every pixel for
+/// basis pair `(i, j)`, scaled by the BlurHash normalisation factor.
+fn basis_factor(pixels: &[u8], width: usize, height: usize, i: usize, j: usize) -> (f64, f64, f64) {
+    let normalisation = if i == 0 && j == 0 { 1.0 } else { 2.0 };
+    let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+
+    for y in 0..height {
+        for x in 0..width {
+            let basis = (std::f64::consts::PI * i as f64 * x as f64 / width as f64).cos()
+                * (std::f64::consts::PI * j as f64 * y as f64 / height as f64).cos();
+            let idx = (y * width + x) * 3;
+            r += basis * srgb_to_linear(pixels[idx]);
+            g += basis * srgb_to_linear(pixels[idx + 1]);
+            b += basis * srgb_to_linear(pixels[idx + 2]);
+        }
+    }
+
+    let scale = normalisation / (width * height) as f64;
+    (r * scale, g * scale, b * scale)
+}
+
+fn encode_blurhash(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    components_x: usize,
+    components_y: usize,
+) -> String {
+    let mut factors = Vec::with_capacity(components_x * components_y);
+    for j in 0..components_y {
+        for i in 0..components_x {
+            factors.push(basis_factor(pixels, width, height, i, j));
+        }
+    }
+
+    let dc = factors[0];
+    let ac = &factors[1..];
+    let max_val = ac
+        .iter()
+        .flat_map(|&(r, g, b)| [r.abs(), g.abs(), b.abs()])
+        .fold(0.0_f64, f64::max);
+
+    let mut hash = String::new();
+
+    let size_flag = (components_x - 1) + (components_y - 1) * 9;
+    hash.push_str(&encode_base83(size_flag as u32, 1));
+
+    let quantised_max = if ac.is_empty() || max_val <= 0.0 {
+        0
+    } else {
+        ((max_val * 166.0 - 0.5).floor() as i64).clamp(0, 82) as u32
+    };
+    hash.push_str(&encode_base83(quantised_max, 1));
+
+    let (dc_r, dc_g, dc_b) = dc;
+    let dc_value = ((linear_to_srgb(dc_r) as u32) << 16)
+        | ((linear_to_srgb(dc_g) as u32) << 8)
+        | (linear_to_srgb(dc_b) as u32);
+    hash.push_str(&encode_base83(dc_value, 4));
+
+    for &(r, g, b) in ac {
+        let value = if max_val > 0.0 {
+            let quantise = |v: f64| -> i64 { ((v / max_val * 9.0 + 9.5).floor() as i64).clamp(0, 18) };
+            quantise(r) * 19 * 19 + quantise(g) * 19 + quantise(b)
+        } else {
+            0
+        };
+        hash.push_str(&encode_base83(value as u32, 2));
+    }
+
+    hash
+}
@@ -0,0 +1,159 @@
+//! Content-addressed dedup/move-detection index. `scanner::has_metadata`
+//! keys everything on filename-adjacent sidecars, so renaming or moving a
+//! video -- or keeping a second copy of it elsewhere in the library --
+//! re-triggers a full TMDB lookup and image download. This keeps a
+//! persistent `hash -> metadata` index (plus which paths produced each
+//! hash) so the scanner can short-circuit the network round trip and
+//! report duplicate file groups.
+
+use crate::metadata::LocalMetadata;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::RwLock;
+
+const CONTENT_HASHES_FILE: &str = "content_hashes.json";
+const CHUNK_SIZE: usize = 16 * 1024;
+/// How much of the start/end of a file the cheap fingerprint mode reads,
+/// instead of hashing the whole thing -- see `AppConfig::cheap_fingerprint`.
+const FINGERPRINT_SAMPLE_BYTES: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct HashEntry {
+    metadata: LocalMetadata,
+    /// Every path that has produced this hash; the first is the "original"
+    /// whose sidecar image is copied to later duplicates.
+    paths: Vec<String>,
+}
+
+/// Keyed by content hash. Loaded once at startup and rewritten to
+/// `content_hashes.json` (mirroring `scan_job::ScanJobStore`'s load-whole
+/// file/write-whole-file persistence) whenever a new hash or duplicate path
+/// is recorded. `pending` is a small in-memory side table so the worker
+/// doesn't have to re-hash a file it was just queued from.
+#[derive(Clone)]
+pub struct ContentHashIndex {
+    entries: Arc<RwLock<HashMap<String, HashEntry>>>,
+    pending: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl ContentHashIndex {
+    pub async fn load() -> Self {
+        let entries = match tokio::fs::read_to_string(CONTENT_HASHES_FILE).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        println!(
+            "[content_hash] Loaded {} content hash(es) from {}",
+            entries.len(),
+            CONTENT_HASHES_FILE
+        );
+        Self {
+            entries: Arc::new(RwLock::new(entries)),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    async fn save(&self) {
+        let entries = self.entries.read().await;
+        if let Ok(content) = serde_json::to_string_pretty(&*entries) {
+            let _ = tokio::fs::write(CONTENT_HASHES_FILE, content).await;
+        }
+    }
+
+    /// Returns the already-known metadata for `hash` plus the path it was
+    /// first recorded against, so the caller can copy metadata/poster to a
+    /// new path instead of hitting TMDB again.
+    pub async fn lookup(&self, hash: &str) -> Option<(LocalMetadata, PathBuf)> {
+        let entries = self.entries.read().await;
+        let entry = entries.get(hash)?;
+        let original = entry.paths.first()?;
+        Some((entry.metadata.clone(), PathBuf::from(original)))
+    }
+
+    /// Records that `path` produced `hash` with the given (freshly fetched
+    /// or copied) metadata. The first path recorded for a hash keeps its
+    /// metadata; later paths are just added to the duplicate group.
+    pub async fn record(&self, hash: &str, path: &Path, metadata: &LocalMetadata) {
+        let path_key = path.to_string_lossy().to_string();
+        {
+            let mut entries = self.entries.write().await;
+            let entry = entries
+                .entry(hash.to_string())
+                .or_insert_with(|| HashEntry {
+                    metadata: metadata.clone(),
+                    paths: Vec::new(),
+                });
+            if !entry.paths.contains(&path_key) {
+                entry.paths.push(path_key);
+            }
+        }
+        self.save().await;
+    }
+
+    /// Notes the hash a file was queued under, so `take_queued_hash` can
+    /// recover it once the worker has actual metadata to record.
+    pub async fn mark_queued(&self, path: &Path, hash: &str) {
+        self.pending
+            .write()
+            .await
+            .insert(path.to_string_lossy().to_string(), hash.to_string());
+    }
+
+    pub async fn take_queued_hash(&self, path: &Path) -> Option<String> {
+        self.pending
+            .write()
+            .await
+            .remove(&path.to_string_lossy().to_string())
+    }
+
+    /// Every hash with more than one known path, for reporting duplicate
+    /// file groups.
+    pub async fn duplicate_groups(&self) -> Vec<Vec<String>> {
+        self.entries
+            .read()
+            .await
+            .values()
+            .filter(|entry| entry.paths.len() > 1)
+            .map(|entry| entry.paths.clone())
+            .collect()
+    }
+}
+
+/// Streaming SHA-256 over `path`, read in `CHUNK_SIZE` chunks so large
+/// files are never held fully in memory. When `cheap` is set (and the file
+/// is large enough for it to matter), hashes only the first and last
+/// `FINGERPRINT_SAMPLE_BYTES` plus the file size instead of the whole
+/// file -- a fast fingerprint rather than a true content hash.
+pub async fn hash_file(path: &Path, cheap: bool) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let mut hasher = Sha256::new();
+
+    if cheap && len > FINGERPRINT_SAMPLE_BYTES * 2 {
+        hasher.update(len.to_le_bytes());
+
+        let mut buf = vec![0u8; FINGERPRINT_SAMPLE_BYTES as usize];
+        file.read_exact(&mut buf).await?;
+        hasher.update(&buf);
+
+        file.seek(std::io::SeekFrom::End(-(FINGERPRINT_SAMPLE_BYTES as i64)))
+            .await?;
+        file.read_exact(&mut buf).await?;
+        hasher.update(&buf);
+    } else {
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
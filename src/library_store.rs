@@ -0,0 +1,238 @@
+//! Persistent storage for `Library` definitions and a path+mtime-keyed cache
+//! of fetched TMDB metadata, backed by SQLite. Mirrors `store::MediaStore`'s
+//! trait-behind-a-backend shape: a `LibraryStore` trait with one real
+//! implementation (`SqliteLibraryStore`), so callers aren't coupled to SQL
+//! directly and a future alternate backend only needs a new impl.
+//!
+//! Not wired into `AppState` yet -- `AppState.libraries` is still the
+//! in-memory `Vec` backed by `routes::library::LIBRARIES_FILE`, and
+//! `metadata::read_local_metadata` still re-reads loose sidecar files on
+//! every call. Swapping those over to `get_metadata`/`put_metadata` (and
+//! `list_libraries`/`save_libraries`) is follow-up work; `import_existing`
+//! is ready for that day, so nothing already scanned gets lost when it
+//! happens.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::metadata::LocalMetadata;
+use crate::models::Library;
+
+/// Persists `Library` rows and a path+mtime-keyed cache of `LocalMetadata`,
+/// so restarts don't lose library definitions and unchanged files don't get
+/// re-fetched from TMDB on every scan.
+#[async_trait]
+pub trait LibraryStore: Send + Sync {
+    async fn list_libraries(&self) -> Result<Vec<Library>>;
+    async fn save_libraries(&self, libraries: &[Library]) -> Result<()>;
+
+    /// Returns the cached metadata for `path` unless `mtime` is newer than
+    /// what was cached -- a newer mtime means the file changed since and the
+    /// caller should treat this as a miss and re-fetch.
+    async fn get_metadata(&self, path: &str, mtime: SystemTime) -> Result<Option<LocalMetadata>>;
+    async fn put_metadata(
+        &self,
+        path: &str,
+        mtime: SystemTime,
+        metadata: &LocalMetadata,
+    ) -> Result<()>;
+}
+
+pub struct SqliteLibraryStore {
+    pool: SqlitePool,
+}
+
+impl SqliteLibraryStore {
+    /// Opens (creating if needed) the SQLite database at `db_path` and runs
+    /// the schema migration.
+    pub async fn open(db_path: &Path) -> Result<Self> {
+        let url = format!("sqlite://{}?mode=rwc", db_path.display());
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect(&url)
+            .await
+            .with_context(|| format!("Failed to open SQLite database at {:?}", db_path))?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS libraries (
+                id TEXT PRIMARY KEY,
+                data TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .context("Failed to create libraries table")?;
+
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS metadata_cache (
+                path TEXT PRIMARY KEY,
+                mtime_unix_secs INTEGER NOT NULL,
+                data TEXT NOT NULL
+            )",
+        )
+        .execute(&pool)
+        .await
+        .context("Failed to create metadata_cache table")?;
+
+        Ok(Self { pool })
+    }
+
+    /// One-time migration: imports `libraries_json_path` (e.g.
+    /// `routes::library::LIBRARIES_FILE`) if the `libraries` table is still
+    /// empty, then walks each imported library's path importing every
+    /// `{file}.json` sidecar it finds into the metadata cache. A no-op once
+    /// the `libraries` table has been populated, so it's safe to call on
+    /// every startup.
+    pub async fn import_existing(&self, libraries_json_path: &Path) -> Result<()> {
+        let existing: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM libraries")
+            .fetch_one(&self.pool)
+            .await
+            .context("Failed to count existing libraries")?;
+        if existing > 0 {
+            return Ok(());
+        }
+
+        let Ok(content) = tokio::fs::read_to_string(libraries_json_path).await else {
+            return Ok(());
+        };
+        let Ok(libraries) = serde_json::from_str::<Vec<Library>>(&content) else {
+            return Ok(());
+        };
+
+        self.save_libraries(&libraries).await?;
+
+        for library in &libraries {
+            self.import_sidecars(&library.path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Recursively imports every `*.json` sidecar under `dir` into the
+    /// metadata cache, keyed by the media file's own path (the sidecar's
+    /// `{file}.json` name minus the `.json` suffix) and the sidecar's own
+    /// mtime -- close enough to the media file's, since both are written
+    /// within the same scan pass.
+    async fn import_sidecars(&self, dir: &Path) -> Result<()> {
+        let mut stack = vec![dir.to_path_buf()];
+        while let Some(current) = stack.pop() {
+            let Ok(mut read_dir) = tokio::fs::read_dir(&current).await else {
+                continue;
+            };
+            while let Ok(Some(entry)) = read_dir.next_entry().await {
+                let path = entry.path();
+                let file_type = entry.file_type().await.ok();
+                if file_type.map(|t| t.is_dir()).unwrap_or(false) {
+                    stack.push(path);
+                    continue;
+                }
+                if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                    continue;
+                }
+                let Ok(meta) = tokio::fs::metadata(&path).await else {
+                    continue;
+                };
+                let Ok(mtime) = meta.modified() else {
+                    continue;
+                };
+                let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                    continue;
+                };
+                let Ok(local_meta) = serde_json::from_str::<LocalMetadata>(&content) else {
+                    continue;
+                };
+
+                let media_path = path.with_extension("");
+                let media_path_str = media_path.to_string_lossy().to_string();
+                let _ = self.put_metadata(&media_path_str, mtime, &local_meta).await;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unix_secs(t: SystemTime) -> i64 {
+    t.duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[async_trait]
+impl LibraryStore for SqliteLibraryStore {
+    async fn list_libraries(&self) -> Result<Vec<Library>> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT data FROM libraries")
+            .fetch_all(&self.pool)
+            .await
+            .context("Failed to list libraries")?;
+
+        rows.into_iter()
+            .map(|(data,)| serde_json::from_str(&data).context("Failed to parse stored library"))
+            .collect()
+    }
+
+    async fn save_libraries(&self, libraries: &[Library]) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .context("Failed to start transaction")?;
+
+        sqlx::query("DELETE FROM libraries")
+            .execute(&mut *tx)
+            .await
+            .context("Failed to clear libraries table")?;
+
+        for library in libraries {
+            let data = serde_json::to_string(library).context("Failed to serialize library")?;
+            sqlx::query("INSERT INTO libraries (id, data) VALUES (?, ?)")
+                .bind(&library.id)
+                .bind(&data)
+                .execute(&mut *tx)
+                .await
+                .context("Failed to insert library")?;
+        }
+
+        tx.commit().await.context("Failed to commit transaction")
+    }
+
+    async fn get_metadata(&self, path: &str, mtime: SystemTime) -> Result<Option<LocalMetadata>> {
+        let row: Option<(String, i64)> =
+            sqlx::query_as("SELECT data, mtime_unix_secs FROM metadata_cache WHERE path = ?")
+                .bind(path)
+                .fetch_optional(&self.pool)
+                .await
+                .context("Failed to read metadata cache")?;
+
+        let Some((data, cached_mtime)) = row else {
+            return Ok(None);
+        };
+        if cached_mtime < unix_secs(mtime) {
+            // File changed since the cache entry was written: treat as a miss.
+            return Ok(None);
+        }
+        Ok(serde_json::from_str(&data).ok())
+    }
+
+    async fn put_metadata(
+        &self,
+        path: &str,
+        mtime: SystemTime,
+        metadata: &LocalMetadata,
+    ) -> Result<()> {
+        let data = serde_json::to_string(metadata).context("Failed to serialize metadata")?;
+        sqlx::query(
+            "INSERT INTO metadata_cache (path, mtime_unix_secs, data) VALUES (?, ?, ?)
+             ON CONFLICT(path) DO UPDATE SET mtime_unix_secs = excluded.mtime_unix_secs, data = excluded.data",
+        )
+        .bind(path)
+        .bind(unix_secs(mtime))
+        .bind(&data)
+        .execute(&self.pool)
+        .await
+        .context("Failed to write metadata cache")?;
+        Ok(())
+    }
+}
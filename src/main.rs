@@ -2,14 +2,15 @@ use anyhow::{Context, Result};
 use axum::{
     Router,
     body::Body,
-    extract::{Json, Query, State},
+    extract::{DefaultBodyLimit, Json, Multipart, Path, Query, State},
     http::{HeaderValue, StatusCode, header},
+    middleware,
     response::{IntoResponse, Response},
-    routing::get,
+    routing::{delete, get, post},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::Arc;
 // use tokio::fs::File; // Removed
@@ -18,6 +19,36 @@ use tokio::sync::Mutex;
 use tokio_util::io::ReaderStream;
 use tower::ServiceBuilder;
 use tower_http::{cors::CorsLayer, services::ServeDir, set_header::SetResponseHeaderLayer};
+use uuid::Uuid;
+
+// `auth`/`models`/`organizer`/`scanner`/`store`/`streaming`/`watcher` and
+// the rest of this block are a second app (login/TOTP, TMDB matching, library
+// scanning/organizing, an RSS feed, an HLS session manager) built around its
+// own `models::AppState`, mounted alongside the plain movies-folder API
+// below starting from `auth::AuthState::new`/`scanner::Scanner::new` in
+// `main` below. It used to duplicate a chunk of this functionality across
+// three separate `Transcoder`/session-manager implementations -- that's
+// been collapsed onto this one; see `streaming::manager::TranscodeManager`.
+//
+// `library_store` stays declared but unmounted: its own doc comment
+// explains why the SQLite-backed store it implements isn't wired into
+// `models::AppState.libraries` yet.
+mod auth;
+mod blurhash;
+mod content_hash;
+mod library_store;
+mod matcher;
+mod metadata;
+mod models;
+mod organizer;
+mod remote;
+mod routes;
+mod scan_job;
+mod scanner;
+mod store;
+mod streaming;
+mod tls;
+mod watcher;
 
 // --- Config ---
 
@@ -31,6 +62,135 @@ pub struct AppConfig {
     pub port: u16,
     #[serde(default = "default_host")]
     pub host: String,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
+    /// Cap applied to `POST /api/upload` via `DefaultBodyLimit`, in bytes.
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: u64,
+}
+
+fn default_max_upload_bytes() -> u64 {
+    20 * 1024 * 1024 * 1024
+}
+
+/// `[encoding]` section of `config.toml`. Governs `stream_video`'s
+/// copy-vs-transcode decision and, when transcoding, which encoder/bitrate/
+/// cap it hands ffmpeg.
+#[derive(Debug, Deserialize, Clone)]
+pub struct EncodingConfig {
+    /// Source video codecs `stream_video` will remux (`-c:v copy`) instead
+    /// of transcoding.
+    #[serde(default = "default_direct_play_codecs")]
+    pub direct_play_codecs: Vec<VideoCodec>,
+    /// Codec to transcode to when the source isn't in
+    /// `direct_play_codecs` or exceeds `max_height`.
+    #[serde(default)]
+    pub video_codec: VideoCodec,
+    #[serde(default)]
+    pub audio_codec: AudioCodec,
+    #[serde(default = "default_video_bitrate_kbps")]
+    pub video_bitrate_kbps: u32,
+    /// Source height above which `stream_video` scales down even a
+    /// direct-play-eligible codec.
+    #[serde(default = "default_max_height")]
+    pub max_height: u32,
+    #[serde(default)]
+    pub hwaccel: HwAccel,
+}
+
+impl Default for EncodingConfig {
+    fn default() -> Self {
+        Self {
+            direct_play_codecs: default_direct_play_codecs(),
+            video_codec: VideoCodec::default(),
+            audio_codec: AudioCodec::default(),
+            video_bitrate_kbps: default_video_bitrate_kbps(),
+            max_height: default_max_height(),
+            hwaccel: HwAccel::default(),
+        }
+    }
+}
+
+fn default_direct_play_codecs() -> Vec<VideoCodec> {
+    vec![VideoCodec::H264, VideoCodec::Hevc]
+}
+
+fn default_video_bitrate_kbps() -> u32 {
+    4000
+}
+
+fn default_max_height() -> u32 {
+    1080
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    Hevc,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    /// ffmpeg encoder name for this codec under `hwaccel`, falling back to
+    /// the software encoder when no hardware backend applies.
+    fn ffmpeg_encoder(self, hwaccel: HwAccel) -> &'static str {
+        match (self, hwaccel) {
+            (VideoCodec::H264, HwAccel::Videotoolbox) => "h264_videotoolbox",
+            (VideoCodec::H264, HwAccel::Nvenc) => "h264_nvenc",
+            (VideoCodec::H264, HwAccel::None) => "libx264",
+            (VideoCodec::Hevc, HwAccel::Videotoolbox) => "hevc_videotoolbox",
+            (VideoCodec::Hevc, HwAccel::Nvenc) => "hevc_nvenc",
+            (VideoCodec::Hevc, HwAccel::None) => "libx265",
+            (VideoCodec::Vp9, _) => "libvpx-vp9",
+            (VideoCodec::Av1, _) => "libaom-av1",
+        }
+    }
+
+    /// ffprobe's `codec_name` spelling, so direct-play decisions can compare
+    /// directly against `probe_video_codec`'s output.
+    fn probe_name(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::Hevc => "hevc",
+            VideoCodec::Vp9 => "vp9",
+            VideoCodec::Av1 => "av1",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    #[default]
+    Aac,
+    Opus,
+    Mp3,
+}
+
+impl AudioCodec {
+    fn ffmpeg_encoder(self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "libopus",
+            AudioCodec::Mp3 => "libmp3lame",
+        }
+    }
+}
+
+/// Hardware encoder backend to prefer, if any. Purely a request to ffmpeg --
+/// if the host lacks the matching hardware/driver, the already-spawned
+/// ffmpeg exits with an encoder error and `stream_video`'s response body
+/// ends early; there is no fallback to the software encoder.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HwAccel {
+    #[default]
+    None,
+    Videotoolbox,
+    Nvenc,
 }
 
 fn default_movies_dir() -> PathBuf {
@@ -50,26 +210,101 @@ fn default_host() -> String {
 }
 
 impl AppConfig {
+    /// Resolves config in layers: defaults, then the discovered (or
+    /// `SRATIM_CONFIG`-pinned) `config.toml` -- each TOML key overwrites its
+    /// matching default, via `AppConfig`'s own `#[serde(default = ...)]`
+    /// fields -- then any `SRATIM_`-prefixed environment variable, so a
+    /// single field can always be overridden without editing a file.
     pub fn load() -> Result<Self> {
-        let config_paths = [
-            PathBuf::from("config.toml"),
-            PathBuf::from("/usr/local/etc/sratim/config.toml"),
-            PathBuf::from("/etc/sratim/config.toml"),
-        ];
-
-        for path in &config_paths {
-            if path.exists() {
-                println!("Loading configuration from: {:?}", path);
-                let content = fs::read_to_string(path)
-                    .with_context(|| format!("Failed to read config file: {:?}", path))?;
-                let config: AppConfig = toml::from_str(&content)
-                    .with_context(|| format!("Failed to parse TOML in: {:?}", path))?;
-                return Ok(config);
+        let config = if let Ok(explicit_path) = std::env::var("SRATIM_CONFIG") {
+            let path = PathBuf::from(explicit_path);
+            println!("Loading configuration from: {:?} (SRATIM_CONFIG)", path);
+            let content = fs::read_to_string(&path).with_context(|| {
+                format!(
+                    "Failed to read config file set via SRATIM_CONFIG: {:?}",
+                    path
+                )
+            })?;
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse TOML in: {:?}", path))?
+        } else {
+            let config_paths = [
+                PathBuf::from("config.toml"),
+                PathBuf::from("/usr/local/etc/sratim/config.toml"),
+                PathBuf::from("/etc/sratim/config.toml"),
+            ];
+
+            let mut found = None;
+            for path in &config_paths {
+                if path.exists() {
+                    println!("Loading configuration from: {:?}", path);
+                    let content = fs::read_to_string(path)
+                        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+                    let config: AppConfig = toml::from_str(&content)
+                        .with_context(|| format!("Failed to parse TOML in: {:?}", path))?;
+                    found = Some(config);
+                    break;
+                }
+            }
+
+            match found {
+                Some(config) => config,
+                None => {
+                    println!("No config file found, using default settings.");
+                    Self::default_settings()
+                }
             }
+        };
+
+        config.with_env_overrides()
+    }
+
+    /// Overlays any `SRATIM_`-prefixed environment variable on top of
+    /// whatever `load` already resolved from defaults/TOML, e.g.
+    /// `SRATIM_PORT`, `SRATIM_MOVIES_DIR`, `SRATIM_HOST`. A value that
+    /// fails to parse fails loudly, naming the offending variable, rather
+    /// than silently falling back to the un-overridden value.
+    fn with_env_overrides(mut self) -> Result<Self> {
+        if let Ok(value) = std::env::var("SRATIM_MOVIES_DIR") {
+            self.movies_dir = PathBuf::from(value);
+        }
+        if let Ok(value) = std::env::var("SRATIM_FRONTEND_DIR") {
+            self.frontend_dir = PathBuf::from(value);
+        }
+        if let Ok(value) = std::env::var("SRATIM_HOST") {
+            self.host = value;
+        }
+        if let Ok(value) = std::env::var("SRATIM_PORT") {
+            self.port = value
+                .parse()
+                .with_context(|| format!("SRATIM_PORT={:?} is not a valid port number", value))?;
+        }
+        if let Ok(value) = std::env::var("SRATIM_MAX_UPLOAD_BYTES") {
+            self.max_upload_bytes = value.parse().with_context(|| {
+                format!(
+                    "SRATIM_MAX_UPLOAD_BYTES={:?} is not a valid byte count",
+                    value
+                )
+            })?;
+        }
+        if let Ok(value) = std::env::var("SRATIM_ENCODING_VIDEO_BITRATE_KBPS") {
+            self.encoding.video_bitrate_kbps = value.parse().with_context(|| {
+                format!(
+                    "SRATIM_ENCODING_VIDEO_BITRATE_KBPS={:?} is not a valid bitrate",
+                    value
+                )
+            })?;
+        }
+        if let Ok(value) = std::env::var("SRATIM_ENCODING_MAX_HEIGHT") {
+            self.encoding.max_height = value.parse().with_context(|| {
+                format!(
+                    "SRATIM_ENCODING_MAX_HEIGHT={:?} is not a valid height",
+                    value
+                )
+            })?;
         }
 
-        println!("No config file found, using default settings.");
-        Ok(Self::default_settings())
+        Ok(self)
     }
 
     fn default_settings() -> Self {
@@ -78,6 +313,8 @@ impl AppConfig {
             frontend_dir: default_frontend_dir(),
             port: default_port(),
             host: default_host(),
+            encoding: EncodingConfig::default(),
+            max_upload_bytes: default_max_upload_bytes(),
         }
     }
 }
@@ -87,29 +324,531 @@ impl AppConfig {
 #[derive(Clone)]
 pub struct AppState {
     pub movies_dir: PathBuf,
-    pub dash_temp_dir: PathBuf,
-    pub ffmpeg_process: Arc<Mutex<Option<Child>>>,
+    pub upload_temp_dir: PathBuf,
+    pub config: Arc<AppConfig>,
+    pub imports: Arc<ImportManager>,
 }
 
-// --- Models ---
+// --- Import ---
+
+/// State of one `/api/import` job, as returned by `GET /api/import/{id}`.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportState {
+    Queued,
+    Downloading,
+    Done,
+    Error,
+}
+
+/// One `/api/import` job. `percent`/`state` are updated in place by the
+/// background task `import_handler` spawns, so `GET /api/import/{id}` always
+/// reads whatever progress has been parsed out of yt-dlp's own output so
+/// far.
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportJob {
+    pub id: String,
+    pub url: String,
+    pub state: ImportState,
+    pub percent: f64,
+    pub title: Option<String>,
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Tracks in-flight/completed `/api/import` jobs, keyed by job id -- a
+/// `Mutex<HashMap<...>>` of `ImportJob`s a background task updates in place.
+pub struct ImportManager {
+    jobs: Mutex<HashMap<String, ImportJob>>,
+}
+
+impl ImportManager {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn create(&self, url: String) -> String {
+        let id = Uuid::new_v4().to_string();
+        let job = ImportJob {
+            id: id.clone(),
+            url,
+            state: ImportState::Queued,
+            percent: 0.0,
+            title: None,
+            file_path: None,
+            error: None,
+        };
+        self.jobs.lock().await.insert(id.clone(), job);
+        id
+    }
+
+    pub async fn get(&self, id: &str) -> Option<ImportJob> {
+        self.jobs.lock().await.get(id).cloned()
+    }
+
+    async fn update(&self, id: &str, f: impl FnOnce(&mut ImportJob)) {
+        if let Some(job) = self.jobs.lock().await.get_mut(id) {
+            f(job);
+        }
+    }
+}
+
+/// Raw shape of the JSON object `yt-dlp -J` prints for a single video --
+/// only the fields `import_handler` actually surfaces are parsed.
+#[derive(Debug, Deserialize)]
+struct YtDlpImportInfo {
+    title: Option<String>,
+    ext: Option<String>,
+}
+
+/// Strips everything but alphanumerics/`-`/`_`/`.` from `title`, so it's
+/// safe to use as a path segment under `movies_dir` regardless of what
+/// yt-dlp reports.
+fn sanitize_filename(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if cleaned.is_empty() {
+        "import".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Runs `yt-dlp`, downloading `job_id`'s URL into `movies_dir`, updating
+/// `imports` with parsed metadata and download progress along the way.
+/// Spawned in the background by `import_handler` so the endpoint itself can
+/// return immediately with just the new job id.
+async fn run_import(imports: Arc<ImportManager>, job_id: String, url: String, movies_dir: PathBuf) {
+    imports
+        .update(&job_id, |job| job.state = ImportState::Downloading)
+        .await;
+
+    let info_output = tokio::process::Command::new("yt-dlp")
+        .args(["-J", "--no-warnings", &url])
+        .output()
+        .await;
+
+    let info: Option<YtDlpImportInfo> = match info_output {
+        Ok(out) if out.status.success() => serde_json::from_slice(&out.stdout).ok(),
+        _ => None,
+    };
 
-// DashStartResponse removed
+    let title = info
+        .as_ref()
+        .and_then(|i| i.title.clone())
+        .unwrap_or_else(|| job_id.clone());
+    let ext = info
+        .as_ref()
+        .and_then(|i| i.ext.clone())
+        .unwrap_or_else(|| "mp4".to_string());
+    let filename = format!("{}.{}", sanitize_filename(&title), ext);
+    let dest_path = movies_dir.join(&filename);
 
-#[derive(Deserialize, Clone)]
-pub struct DashParams {
+    imports.update(&job_id, |job| job.title = Some(title)).await;
+
+    let args = [
+        "-o".to_string(),
+        dest_path.to_string_lossy().to_string(),
+        "--newline".to_string(),
+        "--no-warnings".to_string(),
+        url.clone(),
+    ];
+
+    println!("[import] Spawning yt-dlp: {:?}", args);
+
+    let mut command = tokio::process::Command::new("yt-dlp");
+    command
+        .args(&args)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            imports
+                .update(&job_id, |job| {
+                    job.state = ImportState::Error;
+                    job.error = Some(format!("Failed to spawn yt-dlp: {}", e));
+                })
+                .await;
+            return;
+        }
+    };
+
+    if let Some(stdout) = child.stdout.take() {
+        use tokio::io::{AsyncBufReadExt, BufReader};
+        let mut reader = BufReader::new(stdout);
+        let mut line = String::new();
+        while let Ok(n) = reader.read_line(&mut line).await {
+            if n == 0 {
+                break;
+            }
+            // yt-dlp's `--newline` progress lines look like:
+            // "[download]  42.0% of ..."
+            if let Some(pct) = line
+                .trim()
+                .strip_prefix("[download]")
+                .and_then(|rest| rest.trim().split('%').next())
+                .and_then(|pct| pct.trim().parse::<f64>().ok())
+            {
+                imports.update(&job_id, |job| job.percent = pct).await;
+            }
+            line.clear();
+        }
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(async move {
+            use tokio::io::{AsyncBufReadExt, BufReader};
+            let mut reader = BufReader::new(stderr);
+            let mut line = String::new();
+            while let Ok(n) = reader.read_line(&mut line).await {
+                if n == 0 {
+                    break;
+                }
+                eprint!("[yt-dlp] {}", line);
+                line.clear();
+            }
+        });
+    }
+
+    match child.wait().await {
+        Ok(status) if status.success() => {
+            imports
+                .update(&job_id, |job| {
+                    job.state = ImportState::Done;
+                    job.percent = 100.0;
+                    job.file_path = Some(filename.clone());
+                })
+                .await;
+        }
+        Ok(status) => {
+            imports
+                .update(&job_id, |job| {
+                    job.state = ImportState::Error;
+                    job.error = Some(format!("yt-dlp exited with {}", status));
+                })
+                .await;
+        }
+        Err(e) => {
+            imports
+                .update(&job_id, |job| {
+                    job.state = ImportState::Error;
+                    job.error = Some(format!("Failed to wait on yt-dlp: {}", e));
+                })
+                .await;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportRequest {
+    pub url: String,
+}
+
+/// `POST /api/import`: queues a yt-dlp download of `params.url` into
+/// `movies_dir` and returns its job id immediately; poll
+/// `GET /api/import/{id}` for progress.
+pub async fn import_handler(
+    State(state): State<AppState>,
+    Json(params): Json<ImportRequest>,
+) -> impl IntoResponse {
+    let job_id = state.imports.create(params.url.clone()).await;
+
+    tokio::spawn(run_import(
+        state.imports.clone(),
+        job_id.clone(),
+        params.url,
+        state.movies_dir.clone(),
+    ));
+
+    Json(serde_json::json!({ "id": job_id })).into_response()
+}
+
+/// `GET /api/import/{id}`: reports the current state/percent of a job
+/// queued via `import_handler`.
+pub async fn import_status_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.imports.get(&id).await {
+        Some(job) => Json(job).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+// --- Upload ---
+
+/// Extensions `list_files` will surface and `upload_handler` will accept --
+/// kept as a single list both consult so they never drift apart.
+const ALLOWED_VIDEO_EXTENSIONS: &[&str] =
+    &["mp4", "mkv", "avi", "mov", "webm", "m4v", "flv", "wmv"];
+
+fn has_allowed_extension(file_name: &str) -> bool {
+    std::path::Path::new(file_name)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| ALLOWED_VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[derive(Serialize)]
+pub struct UploadResponse {
     pub path: String,
-    #[serde(default)]
-    pub start: f64,
-    #[serde(rename = "audioTrack")]
-    pub audio_track: Option<usize>,
 }
 
+/// `POST /api/upload`: accepts a multipart form with a `path` text field
+/// (the target subfolder under `movies_dir`, empty for the root) and a
+/// `file` field, streamed to a temp file under `upload_temp_dir` so the
+/// whole upload is never held in memory. The cap on total body size comes
+/// from `AppConfig.max_upload_bytes` via the `DefaultBodyLimit` layer on
+/// this route.
+///
+/// After the upload completes, the temp file is probed with the same
+/// `probe_duration` helper `stream_video` uses --
+/// anything ffprobe can't parse is rejected rather than accepted as a
+/// silently-broken file -- then moved into place with the same
+/// extension allowlist and canonicalization path-escape check `list_files`
+/// performs.
+pub async fn upload_handler(State(state): State<AppState>, mut multipart: Multipart) -> Response {
+    let mut target_dir = String::new();
+    let mut original_name: Option<String> = None;
+    let temp_path = state
+        .upload_temp_dir
+        .join(format!("upload-{}", Uuid::new_v4()));
+
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Multipart error: {}", e);
+                let _ = tokio::fs::remove_file(&temp_path).await;
+                return (StatusCode::BAD_REQUEST, "Invalid multipart body").into_response();
+            }
+        };
+
+        match field.name().unwrap_or("") {
+            "path" => {
+                target_dir = field.text().await.unwrap_or_default();
+            }
+            "file" => {
+                original_name = field.file_name().map(|s| s.to_string());
+                let mut field = field;
+
+                let mut file = match tokio::fs::File::create(&temp_path).await {
+                    Ok(file) => file,
+                    Err(e) => {
+                        eprintln!("Failed to create upload temp file: {}", e);
+                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                    }
+                };
+
+                use tokio::io::AsyncWriteExt;
+                loop {
+                    match field.chunk().await {
+                        Ok(Some(chunk)) => {
+                            if let Err(e) = file.write_all(&chunk).await {
+                                eprintln!("Failed to write upload chunk: {}", e);
+                                let _ = tokio::fs::remove_file(&temp_path).await;
+                                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            eprintln!("Multipart read error: {}", e);
+                            let _ = tokio::fs::remove_file(&temp_path).await;
+                            return (StatusCode::BAD_REQUEST, "Upload was interrupted")
+                                .into_response();
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(original_name) = original_name else {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return (StatusCode::BAD_REQUEST, "Missing \"file\" field").into_response();
+    };
+
+    // The client-supplied filename is attacker-controlled (it's just the
+    // multipart `Content-Disposition: filename=...`) -- reduce it to its
+    // final path component so a value like "../../etc/cron.d/evil.mp4"
+    // can't walk the result out of `dest_dir` below. `has_allowed_extension`
+    // alone doesn't catch this since `Path::extension()` only looks at the
+    // last component too.
+    let Some(original_name) = std::path::Path::new(&original_name)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+    else {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return (StatusCode::BAD_REQUEST, "Invalid file name").into_response();
+    };
+
+    if !has_allowed_extension(&original_name) {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Unsupported file extension",
+        )
+            .into_response();
+    }
+
+    if probe_duration(&temp_path).await.is_none() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Uploaded file could not be parsed as a video",
+        )
+            .into_response();
+    }
+
+    let mut dest_dir = state.movies_dir.clone();
+    if !target_dir.is_empty() {
+        dest_dir.push(&target_dir);
+    }
+    if let Err(e) = tokio::fs::create_dir_all(&dest_dir).await {
+        eprintln!("Failed to create upload target dir: {}", e);
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    // Security check: ensure target_dir didn't escape movies_dir (same
+    // pattern list_files uses).
+    let (Ok(canonical_dest_dir), Ok(canonical_root)) =
+        (dest_dir.canonicalize(), state.movies_dir.canonicalize())
+    else {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    if !canonical_dest_dir.starts_with(&canonical_root) {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    let dest_path = canonical_dest_dir.join(&original_name);
+    if let Err(e) = tokio::fs::rename(&temp_path, &dest_path).await {
+        eprintln!("Failed to move uploaded file into place: {}", e);
+        let _ = tokio::fs::remove_file(&temp_path).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    let rel_path = if target_dir.is_empty() {
+        original_name
+    } else {
+        format!("{}/{}", target_dir, original_name)
+    };
+
+    Json(UploadResponse { path: rel_path }).into_response()
+}
+
+// --- Models ---
+
 #[derive(Deserialize)]
 pub struct SubtitleParams {
     pub path: String,
     pub index: usize,
 }
 
+#[derive(Deserialize)]
+pub struct SubtitleTracksParams {
+    pub path: String,
+}
+
+/// One subtitle stream, as surfaced by `/api/subtitle-tracks` for the
+/// frontend to build a track menu. `index` is the position among subtitle
+/// streams only (what `-map 0:s:{index}` expects), not ffprobe's absolute
+/// stream index.
+#[derive(Serialize)]
+pub struct SubtitleTrackInfo {
+    pub index: usize,
+    pub codec: String,
+    pub language: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Subtitle codecs that render to a bitmap rather than text -- `get_subtitles`
+/// can't convert these to WebVTT, so it rejects them with a 415 instead of
+/// handing ffmpeg a conversion it can't perform.
+const IMAGE_SUBTITLE_CODECS: &[&str] =
+    &["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+#[derive(Deserialize)]
+struct FFProbeSubtitleTags {
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeSubtitleStream {
+    codec_name: Option<String>,
+    #[serde(default)]
+    tags: Option<FFProbeSubtitleTags>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeSubtitleStreamsOutput {
+    streams: Vec<FFProbeSubtitleStream>,
+}
+
+/// Lists `path`'s subtitle streams in `-map 0:s:N` order. `-select_streams s`
+/// already only returns subtitle streams, so ffprobe's own output order is
+/// the one ffmpeg's per-type `N` counts against.
+async fn probe_subtitle_tracks(path: &std::path::Path) -> Vec<SubtitleTrackInfo> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(&[
+            "-v",
+            "error",
+            "-select_streams",
+            "s",
+            "-show_entries",
+            "stream=index,codec_name:stream_tags=language,title",
+            "-of",
+            "json",
+        ])
+        .arg(path)
+        .output()
+        .await;
+
+    let Ok(output) = output else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    let Ok(parsed) = serde_json::from_slice::<FFProbeSubtitleStreamsOutput>(&output.stdout) else {
+        return Vec::new();
+    };
+
+    parsed
+        .streams
+        .into_iter()
+        .enumerate()
+        .map(|(index, stream)| SubtitleTrackInfo {
+            index,
+            codec: stream.codec_name.unwrap_or_default(),
+            language: stream.tags.as_ref().and_then(|t| t.language.clone()),
+            title: stream.tags.as_ref().and_then(|t| t.title.clone()),
+        })
+        .collect()
+}
+
 // --- Handlers ---
 
 #[derive(Deserialize)]
@@ -180,23 +919,12 @@ pub async fn list_files(
                     path: rel_path,
                     entry_type: "folder".to_string(),
                 });
-            } else if is_file {
-                // Filter extensions
-                if let Some(ext) = std::path::Path::new(&file_name)
-                    .extension()
-                    .and_then(|s| s.to_str())
-                {
-                    match ext.to_lowercase().as_str() {
-                        "mp4" | "mkv" | "avi" | "mov" | "webm" | "m4v" | "flv" | "wmv" => {
-                            entries.push(FileEntry {
-                                name: file_name,
-                                path: rel_path,
-                                entry_type: "file".to_string(),
-                            });
-                        }
-                        _ => {}
-                    }
-                }
+            } else if is_file && has_allowed_extension(&file_name) {
+                entries.push(FileEntry {
+                    name: file_name,
+                    path: rel_path,
+                    entry_type: "file".to_string(),
+                });
             }
         }
     }
@@ -289,6 +1017,32 @@ async fn probe_has_audio(path: &std::path::Path) -> bool {
     }
 }
 
+// Separate clean probe for source height, used to decide whether a
+// direct-play-eligible codec still needs scaling down to `max_height`.
+async fn probe_video_height(path: &std::path::Path) -> Option<u32> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(&[
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-show_entries",
+            "stream=height",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+
+    if output.status.success() {
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        None
+    }
+}
+
 // Separate clean probe for duration
 async fn probe_duration(path: &std::path::Path) -> Option<f64> {
     let output = tokio::process::Command::new("ffprobe")
@@ -326,10 +1080,25 @@ pub async fn stream_video(
     let codec_name = probe_video_codec(&abs_path).await;
     let has_audio = probe_has_audio(&abs_path).await;
     let duration = probe_duration(&abs_path).await.unwrap_or(0.0);
+    let source_height = probe_video_height(&abs_path).await;
+
+    let encoding = &state.config.encoding;
+    let within_direct_play_codecs = encoding
+        .direct_play_codecs
+        .iter()
+        .any(|codec| codec.probe_name() == codec_name);
+    let within_cap = source_height.is_none_or(|h| h <= encoding.max_height);
+    let direct_play = within_direct_play_codecs && within_cap;
+
+    let profile = if direct_play {
+        "direct-play".to_string()
+    } else {
+        format!("{:?}", encoding.video_codec).to_lowercase()
+    };
 
     println!(
-        "Detected for {}: Codec={}, Audio={}, Duration={:.2}s",
-        params.path, codec_name, has_audio, duration
+        "Detected for {}: Codec={}, Audio={}, Duration={:.2}s, Height={:?}, Profile={}",
+        params.path, codec_name, has_audio, duration, source_height, profile
     );
 
     let mut args = vec![
@@ -339,14 +1108,30 @@ pub async fn stream_video(
         abs_path.to_string_lossy().to_string(),
         "-map".to_string(),
         "0:v:0".to_string(),
-        "-c:v".to_string(),
-        "copy".to_string(),
     ];
 
-    // Only add hvc1 tag if it's HEVC.
-    if codec_name == "hevc" {
-        args.push("-tag:v".to_string());
-        args.push("hvc1".to_string());
+    if direct_play {
+        args.push("-c:v".to_string());
+        args.push("copy".to_string());
+        // Only add hvc1 tag if it's HEVC.
+        if codec_name == "hevc" {
+            args.push("-tag:v".to_string());
+            args.push("hvc1".to_string());
+        }
+    } else {
+        args.push("-c:v".to_string());
+        args.push(
+            encoding
+                .video_codec
+                .ffmpeg_encoder(encoding.hwaccel)
+                .to_string(),
+        );
+        args.push("-b:v".to_string());
+        args.push(format!("{}k", encoding.video_bitrate_kbps));
+        if source_height.is_some_and(|h| h > encoding.max_height) {
+            args.push("-vf".to_string());
+            args.push(format!("scale=-2:{}", encoding.max_height));
+        }
     }
 
     if has_audio {
@@ -354,7 +1139,7 @@ pub async fn stream_video(
             "-map".to_string(),
             "0:a:0".to_string(),
             "-c:a".to_string(),
-            "aac".to_string(),
+            encoding.audio_codec.ffmpeg_encoder().to_string(),
             "-ac".to_string(),
             "2".to_string(),
         ]);
@@ -407,6 +1192,7 @@ pub async fn stream_video(
                 .header("X-Video-Codec", codec_name) // Signal codec to frontend
                 .header("X-Has-Audio", if has_audio { "true" } else { "false" }) // Signal audio presence
                 .header("X-Video-Duration", duration.to_string()) // Signal duration
+                .header("X-Transcode-Profile", profile) // Signal copy vs. transcode profile
                 .body(Body::from_stream(process_stream))
                 .unwrap()
         }
@@ -417,14 +1203,105 @@ pub async fn stream_video(
     }
 }
 
+/// Lists `params.path`'s subtitle streams for the frontend's track menu. See
+/// `probe_subtitle_tracks` for how `index` maps onto `get_subtitles`' own
+/// `index` query param.
+pub async fn list_subtitle_tracks(
+    State(state): State<AppState>,
+    Query(params): Query<SubtitleTracksParams>,
+) -> impl IntoResponse {
+    let abs_path = state.movies_dir.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    Json(probe_subtitle_tracks(&abs_path).await).into_response()
+}
+
+/// Converts subtitle stream `params.index` of `params.path` to WebVTT and
+/// streams it, mirroring `stream_video`'s ffmpeg-pipe setup. Image-based
+/// subtitle codecs (pgs, dvdsub, ...) can't be converted to WebVTT, so those
+/// are rejected up front with a 415 instead of handing ffmpeg a conversion
+/// that would fail or emit garbage.
 pub async fn get_subtitles(
     State(state): State<AppState>,
     Query(params): Query<SubtitleParams>,
-) -> impl IntoResponse {
-    // Stub: not implemented
-    let _ = state;
-    let _ = params;
-    StatusCode::NOT_FOUND.into_response()
+) -> Response {
+    let abs_path = state.movies_dir.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let tracks = probe_subtitle_tracks(&abs_path).await;
+    let Some(track) = tracks.get(params.index) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    if IMAGE_SUBTITLE_CODECS.contains(&track.codec.as_str()) {
+        return (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "Subtitle track {} is image-based ({}) and can't be converted to WebVTT",
+                params.index, track.codec
+            ),
+        )
+            .into_response();
+    }
+
+    let args = vec![
+        "-i".to_string(),
+        abs_path.to_string_lossy().to_string(),
+        "-map".to_string(),
+        format!("0:s:{}", params.index),
+        "-f".to_string(),
+        "webvtt".to_string(),
+        "pipe:1".to_string(),
+    ];
+
+    println!("[subtitle] Spawning ffmpeg: {:?}", args);
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .args(&args)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true);
+
+    match command.spawn() {
+        Ok(mut child) => {
+            let stdout = child.stdout.take().unwrap();
+            let stderr = child.stderr.take().unwrap();
+
+            tokio::spawn(async move {
+                use tokio::io::{AsyncBufReadExt, BufReader};
+                let mut reader = BufReader::new(stderr);
+                let mut line = String::new();
+                while let Ok(n) = reader.read_line(&mut line).await {
+                    if n == 0 {
+                        break;
+                    }
+                    eprint!("[ffmpeg-subtitle] {}", line);
+                    line.clear();
+                }
+            });
+
+            let stream = ReaderStream::new(stdout);
+            let process_stream = ProcessStream {
+                stream,
+                _child: child,
+            };
+
+            Response::builder()
+                .header("Content-Type", "text/vtt")
+                .header("Cache-Control", "no-cache")
+                .body(Body::from_stream(process_stream))
+                .unwrap()
+        }
+        Err(e) => {
+            eprintln!("Failed to spawn ffmpeg for subtitles: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
 }
 
 // --- Main ---
@@ -440,19 +1317,188 @@ async fn main() {
         std::fs::create_dir_all(&movies_dir).expect("Failed to create movies directory");
     }
 
-    let dash_temp_dir = std::env::temp_dir().join("sratim_dash");
-    std::fs::create_dir_all(&dash_temp_dir).expect("Failed to create dash temp directory");
+    let upload_temp_dir = std::env::temp_dir().join("sratim_uploads");
+    std::fs::create_dir_all(&upload_temp_dir).expect("Failed to create upload temp directory");
+
+    let imports = Arc::new(ImportManager::new());
+    let max_upload_bytes = config.max_upload_bytes as usize;
 
     let shared_state = AppState {
         movies_dir: movies_dir.clone(),
-        dash_temp_dir,
+        upload_temp_dir,
+        config: Arc::new(config),
+        imports,
+    };
+
+    // The auth/library/scanning app (`models::AppState`): login/TOTP,
+    // library scanning/watching, and the HLS session manager
+    // (`streaming::manager::TranscodeManager`) that's now the crate's one
+    // segmented-streaming implementation. Parses the first `config.toml`
+    // found on the same search path into a distinct `AppConfig` from the one
+    // above, but without the `SRATIM_CONFIG` pin or `SRATIM_` env overrides.
+    let core_config = crate::models::AppConfig::load()
+        .expect("Failed to load library/auth subsystem configuration");
+    let tls_active = crate::tls::tls_active(&core_config);
+    let auth_state = crate::auth::AuthState::new(tls_active).await;
+    let libraries = crate::routes::library::load_libraries().await;
+    let (scanner, _scan_worker) = crate::scanner::Scanner::new(core_config.clone()).await;
+    let scanner = Arc::new(scanner);
+    let watcher = Arc::new(scanner.spawn_watcher(libraries.clone()));
+    let hls_manager = Arc::new(crate::streaming::manager::TranscodeManager::new());
+    crate::streaming::manager::spawn_reaper(hls_manager.clone());
+    let hls_temp_dir = std::env::temp_dir().join("sratim_hls");
+    std::fs::create_dir_all(&hls_temp_dir).expect("Failed to create hls temp directory");
+
+    let core_state = crate::models::AppState {
+        dash_temp_dir: std::env::temp_dir(),
         ffmpeg_process: Arc::new(Mutex::new(None)),
+        auth: auth_state,
+        libraries: Arc::new(tokio::sync::RwLock::new(libraries)),
+        config: core_config,
+        scanner,
+        watcher,
+        hls: hls_manager,
+        hls_temp_dir,
     };
 
+    // User-management routes, gated by `auth::require_admin` (which needs
+    // `auth::auth_middleware` to have already populated the `Claims`
+    // extension it reads) per `require_admin`'s own doc comment.
+    let auth_admin_router = Router::new()
+        .route(
+            "/users",
+            get(crate::auth::list_users_handler).post(crate::auth::create_user_handler),
+        )
+        .route(
+            "/users/{username}",
+            delete(crate::auth::delete_user_handler),
+        )
+        .route(
+            "/users/{username}/password",
+            post(crate::auth::admin_change_password_handler),
+        )
+        .route(
+            "/users/{username}/totp/reset",
+            post(crate::auth::reset_totp_handler),
+        )
+        .layer(middleware::from_fn(crate::auth::require_admin))
+        .layer(middleware::from_fn_with_state(
+            core_state.clone(),
+            crate::auth::auth_middleware,
+        ));
+    let auth_public_router = Router::new()
+        .route("/login", post(crate::auth::login_handler))
+        .route("/refresh", post(crate::auth::refresh_handler))
+        .route("/verify-totp", post(crate::auth::verify_totp_handler))
+        .route("/register", post(crate::auth::register_handler))
+        .route("/logout", post(crate::auth::logout_handler))
+        .route("/me", get(crate::auth::me_handler))
+        .route(
+            "/change-password",
+            post(crate::auth::change_password_handler),
+        )
+        .route("/totp/enroll", post(crate::auth::enroll_totp_handler))
+        .route("/totp/confirm", post(crate::auth::confirm_totp_handler));
+    let auth_router = auth_public_router
+        .merge(auth_admin_router)
+        .with_state(core_state.clone());
+
+    // Library-management routes. `get_libraries`/`feed_handler`/
+    // `serve_content` stay public (the latter two are fetched by
+    // clients -- browsers and podcast apps -- that can't run through
+    // `auth_middleware`'s cookie/bearer check); everything that creates,
+    // deletes, scans, organizes, or mints a feed token is admin-only (the
+    // scan/organize/feed-token handlers each carry an "Intended to be layered
+    // behind auth::require_admin" doc comment).
+    let library_admin_router = Router::new()
+        .route("/", post(crate::routes::library::create_library))
+        .route("/{id}", delete(crate::routes::library::delete_library))
+        .route(
+            "/{id}/scan",
+            post(crate::routes::library::scan_library).get(crate::routes::library::scan_status),
+        )
+        .route(
+            "/{id}/organize",
+            post(crate::routes::library::organize_path),
+        )
+        .route(
+            "/{id}/feed-token",
+            post(crate::routes::feed::feed_token_handler),
+        )
+        .layer(middleware::from_fn(crate::auth::require_admin))
+        .layer(middleware::from_fn_with_state(
+            core_state.clone(),
+            crate::auth::auth_middleware,
+        ));
+    let library_public_router = Router::new()
+        .route("/", get(crate::routes::library::get_libraries))
+        .route("/{id}/feed.xml", get(crate::routes::feed::feed_handler))
+        .route(
+            "/{id}/content/{*file_path}",
+            get(crate::routes::library::serve_content),
+        );
+    let library_router = library_public_router
+        .merge(library_admin_router)
+        .with_state(core_state.clone());
+
+    // `routes::video`'s HLS session endpoints -- the literal paths
+    // `start_hls`'s own doc comment advertises (`GET
+    // /api/hls/{session_id}/playlist.m3u8`).
+    let hls_router = Router::new()
+        .route("/start", get(crate::routes::video::start_hls))
+        .route(
+            "/{session_id}/playlist.m3u8",
+            get(crate::routes::video::get_hls_playlist),
+        )
+        .route(
+            "/{session_id}/{segment}",
+            get(crate::routes::video::get_hls_segment),
+        )
+        .route("/stop", post(crate::routes::video::stop_hls))
+        .with_state(core_state.clone());
+
+    // The rest of `routes::video`'s library-aware playback endpoints.
+    // `lookup_metadata` self-checks `Extension<Claims>` for
+    // `claims.is_admin`, so it needs `auth_middleware` layered (to populate
+    // that extension) even though it isn't behind `require_admin` itself.
+    let video_admin_router = Router::new()
+        .route("/lookup", get(crate::routes::video::lookup_metadata))
+        .layer(middleware::from_fn_with_state(
+            core_state.clone(),
+            crate::auth::auth_middleware,
+        ));
+    let video_public_router = Router::new()
+        .route("/files", get(crate::routes::video::list_files))
+        .route("/metadata", get(crate::routes::video::get_metadata))
+        .route("/streams", get(crate::routes::video::get_streams))
+        .route("/thumbnail", get(crate::routes::video::get_thumbnail))
+        .route("/stream", get(crate::routes::video::stream_video))
+        .route("/subtitles", get(crate::routes::video::get_subtitles))
+        .route("/subtitle-list", get(crate::routes::video::list_subtitles))
+        .route("/progress", get(crate::routes::video::get_progress));
+    let video_router = video_public_router
+        .merge(video_admin_router)
+        .with_state(core_state.clone());
+
     let app = Router::new()
+        .nest("/api/auth", auth_router)
+        .nest("/api/libraries", library_router)
+        .nest("/api/hls", hls_router)
+        .nest("/api/v2", video_router)
+        .route(
+            "/api/browse",
+            get(crate::routes::library::browse_filesystem),
+        )
         .route("/api/movies", get(list_files))
         .route("/api/stream", get(stream_video))
         .route("/api/subtitles", get(get_subtitles))
+        .route("/api/subtitle-tracks", get(list_subtitle_tracks))
+        .route("/api/import", post(import_handler))
+        .route("/api/import/{id}", get(import_status_handler))
+        .route(
+            "/api/upload",
+            post(upload_handler).layer(DefaultBodyLimit::max(max_upload_bytes)),
+        )
         .nest_service("/content", ServeDir::new(&movies_dir))
         .fallback_service(
             ServiceBuilder::new()
@@ -462,16 +1508,12 @@ async fn main() {
                         "no-store, no-cache, must-revalidate, proxy-revalidate, max-age=0",
                     ),
                 ))
-                .service(ServeDir::new(&config.frontend_dir)),
+                .service(ServeDir::new(&shared_state.config.frontend_dir)),
         )
         .layer(CorsLayer::permissive())
-        .with_state(shared_state);
+        .with_state(shared_state.clone());
 
-    let addr: SocketAddr = format!("{}:{}", config.host, config.port)
-        .parse()
-        .expect("Invalid host/port");
-
-    println!("Server running on http://{}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    crate::tls::serve(app, &core_state.config)
+        .await
+        .expect("Server error");
 }
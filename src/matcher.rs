@@ -0,0 +1,296 @@
+//! Robust filename matching for the scanner. Replaces the inline
+//! `SxxExx`/season regexes in `scanner.rs`, which only recognize one shape
+//! and silently skip everything else (`Show.1x01`, absolute-numbered anime
+//! episodes like `Show - 045`, titles with embedded years/quality tags).
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Parsed identity of a media filename.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MatchResult {
+    pub title: String,
+    pub year: Option<u16>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub episode_absolute: Option<u32>,
+}
+
+/// Extracts a season number from a folder name like `Season 01` or `S01`.
+pub fn match_season_folder(folder_name: &str) -> Option<u32> {
+    static SEASON_RE: OnceLock<Regex> = OnceLock::new();
+    let re = SEASON_RE.get_or_init(|| Regex::new(r"(?i)season\s*(\d+)|\bs(\d+)\b").unwrap());
+    re.captures(folder_name)
+        .and_then(|caps| caps.get(1).or_else(|| caps.get(2)))
+        .and_then(|m| m.as_str().parse().ok())
+}
+
+/// Parses `file_name` into a title plus whatever season/episode/year
+/// information can be found.
+///
+/// `known_season` lets callers scanning inside a season folder (where the
+/// season number is already known from the directory name) recognize bare
+/// `E02`/`Ep02` forms that don't repeat the season. `allow_absolute` accepts
+/// a bare number as an absolute episode count when no season/episode pair
+/// was found, for single-season or anime libraries numbered that way.
+pub fn match_filename(file_name: &str, known_season: Option<u32>, allow_absolute: bool) -> MatchResult {
+    let stem = std::path::Path::new(file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_name.to_string());
+
+    let year = find_year(&stem);
+    let (season, episode) = find_season_episode(&stem, known_season);
+    let episode_absolute = if season.is_none() && episode.is_none() && allow_absolute {
+        find_absolute_episode(&stem)
+    } else {
+        None
+    };
+
+    MatchResult {
+        title: clean_title(&stem),
+        year,
+        season,
+        episode,
+        episode_absolute,
+    }
+}
+
+/// Finds a 1900-2099 year, skipping candidates immediately followed by `p`
+/// or `i` (resolution/scan-type tags like `2160p` rather than a real year).
+fn find_year(stem: &str) -> Option<u16> {
+    static YEAR_RE: OnceLock<Regex> = OnceLock::new();
+    let re = YEAR_RE.get_or_init(|| Regex::new(r"(19|20)\d{2}").unwrap());
+
+    for mat in re.find_iter(stem) {
+        let next_char = stem[mat.end()..].chars().next();
+        let looks_like_resolution_tag =
+            matches!(next_char, Some(c) if c.eq_ignore_ascii_case(&'p') || c.eq_ignore_ascii_case(&'i'));
+        if !looks_like_resolution_tag {
+            return mat.as_str().parse::<u16>().ok();
+        }
+    }
+    None
+}
+
+/// Tries, in order: `S01E02`, `1x02`, then (only if `known_season` is set)
+/// a bare `E02`/`Ep02`.
+fn find_season_episode(stem: &str, known_season: Option<u32>) -> (Option<u32>, Option<u32>) {
+    static SXXEXX_RE: OnceLock<Regex> = OnceLock::new();
+    let sxxexx_re =
+        SXXEXX_RE.get_or_init(|| Regex::new(r"(?i)[sS](\d{1,2})[._ -]?[eE](\d{1,3})").unwrap());
+    if let Some(caps) = sxxexx_re.captures(stem) {
+        if let (Ok(s), Ok(e)) = (caps[1].parse(), caps[2].parse()) {
+            return (Some(s), Some(e));
+        }
+    }
+
+    static NXNN_RE: OnceLock<Regex> = OnceLock::new();
+    let nxnn_re = NXNN_RE.get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})x(\d{1,3})\b").unwrap());
+    if let Some(caps) = nxnn_re.captures(stem) {
+        if let (Ok(s), Ok(e)) = (caps[1].parse(), caps[2].parse()) {
+            return (Some(s), Some(e));
+        }
+    }
+
+    if let Some(season) = known_season {
+        static BARE_EP_RE: OnceLock<Regex> = OnceLock::new();
+        let bare_ep_re =
+            BARE_EP_RE.get_or_init(|| Regex::new(r"(?i)\b(?:e|ep)\.?\s*(\d{1,3})\b").unwrap());
+        if let Some(caps) = bare_ep_re.captures(stem) {
+            if let Ok(e) = caps[1].parse() {
+                return (Some(season), Some(e));
+            }
+        }
+    }
+
+    (None, None)
+}
+
+/// Falls back to the last bare 2-4 digit number in the name as an absolute
+/// episode count (e.g. `Show - 045`), for libraries with no season folders.
+fn find_absolute_episode(stem: &str) -> Option<u32> {
+    static ABS_RE: OnceLock<Regex> = OnceLock::new();
+    let re = ABS_RE.get_or_init(|| Regex::new(r"(?:^|[-_.\s])(\d{2,4})(?:[-_.\s]|$)").unwrap());
+    re.captures_iter(stem)
+        .last()
+        .and_then(|caps| caps[1].parse().ok())
+}
+
+/// Case-insensitive whole-word clutter markers matching filebot's sample/
+/// trailer/extras exclusion list. `.` in each pattern is a normal regex
+/// wildcard, so `deleted.scenes` matches `Deleted.Scenes`, `Deleted Scenes`,
+/// and `Deleted-Scenes` alike.
+const BUILTIN_CLUTTER_PATTERNS: &[&str] = &[
+    "sample",
+    "trailer",
+    "extras",
+    "deleted.scenes",
+    "featurette",
+    "behind.the.scenes",
+    "music.video",
+    "scrapbook",
+];
+
+/// Minimum size a video file needs to avoid being treated as clutter on size
+/// alone -- catches unnamed sample clips that slip past `is_clutter_name`.
+pub const CLUTTER_MIN_SIZE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Whether `name` (a file or directory name) matches a built-in or
+/// library-configured (`extra_patterns`) clutter pattern. Doesn't look at
+/// file size; pair with `is_clutter` for that.
+pub fn is_clutter_name(name: &str, extra_patterns: &[String]) -> bool {
+    static CLUTTER_RE: OnceLock<Regex> = OnceLock::new();
+    let re = CLUTTER_RE.get_or_init(|| {
+        let pattern = format!(r"(?i)\b({})\b", BUILTIN_CLUTTER_PATTERNS.join("|"));
+        Regex::new(&pattern).unwrap()
+    });
+    if re.is_match(name) {
+        return true;
+    }
+    extra_patterns.iter().any(|pattern| {
+        Regex::new(&format!(r"(?i)\b{}\b", pattern))
+            .map(|re| re.is_match(name))
+            .unwrap_or(false)
+    })
+}
+
+/// Whether a video file should be treated as clutter: either its name
+/// matches `is_clutter_name`, or it's small enough to be a sample clip that
+/// wasn't named as one.
+pub fn is_clutter(file_name: &str, size_bytes: u64, extra_patterns: &[String]) -> bool {
+    size_bytes < CLUTTER_MIN_SIZE_BYTES || is_clutter_name(file_name, extra_patterns)
+}
+
+/// Strips bracketed release-group/quality tags, cuts at the first
+/// season/episode/year marker, and strips any quality/codec tags that
+/// weren't bracketed.
+fn clean_title(stem: &str) -> String {
+    static BRACKETS_RE: OnceLock<Regex> = OnceLock::new();
+    let brackets_re = BRACKETS_RE.get_or_init(|| Regex::new(r"[\[(][^\])]*[\])]").unwrap());
+    let no_brackets = brackets_re.replace_all(stem, " ");
+
+    static CUT_RE: OnceLock<Regex> = OnceLock::new();
+    let cut_re = CUT_RE.get_or_init(|| {
+        Regex::new(r"(?i)[sS]\d{1,2}[._ -]?[eE]\d{1,3}|\b\d{1,2}x\d{1,3}\b|(19|20)\d{2}").unwrap()
+    });
+    let title_part = match cut_re.find(&no_brackets) {
+        Some(mat) => &no_brackets[..mat.start()],
+        None => &no_brackets,
+    };
+
+    static TAGS_RE: OnceLock<Regex> = OnceLock::new();
+    let tags_re = TAGS_RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)\b(480p|720p|1080p|2160p|4k|8k|bluray|blu-ray|bdrip|brrip|web-?dl|webrip|web|hdtv|dvdrip|remux|hdr10?|dv|x264|x265|h264|h265|hevc|avc|aac\d?|ac3|dts(-hd)?|truehd|atmos|\d+bit)\b",
+        )
+        .unwrap()
+    });
+    let no_tags = tags_re.replace_all(title_part, " ");
+
+    let no_separators = no_tags.replace(['.', '_'], " ");
+
+    static SPACE_RE: OnceLock<Regex> = OnceLock::new();
+    let space_re = SPACE_RE.get_or_init(|| Regex::new(r"\s+").unwrap());
+    space_re.replace_all(&no_separators, " ").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sxxexx() {
+        let m = match_filename("Show.Name.S01E02.1080p.WEB-DL.x265.mkv", None, false);
+        assert_eq!(m.title, "Show Name");
+        assert_eq!(m.season, Some(1));
+        assert_eq!(m.episode, Some(2));
+        assert_eq!(m.episode_absolute, None);
+    }
+
+    #[test]
+    fn test_nxnn() {
+        let m = match_filename("Show.1x01.mkv", None, false);
+        assert_eq!(m.title, "Show");
+        assert_eq!(m.season, Some(1));
+        assert_eq!(m.episode, Some(1));
+    }
+
+    #[test]
+    fn test_bare_episode_with_known_season() {
+        let m = match_filename("Show - Ep02.mkv", Some(3), false);
+        assert_eq!(m.season, Some(3));
+        assert_eq!(m.episode, Some(2));
+    }
+
+    #[test]
+    fn test_bare_episode_without_known_season_is_unmatched() {
+        let m = match_filename("Show - Ep02.mkv", None, false);
+        assert_eq!(m.season, None);
+        assert_eq!(m.episode, None);
+    }
+
+    #[test]
+    fn test_absolute_episode_fallback() {
+        let m = match_filename("Show - 045.mkv", None, true);
+        assert_eq!(m.season, None);
+        assert_eq!(m.episode, None);
+        assert_eq!(m.episode_absolute, Some(45));
+    }
+
+    #[test]
+    fn test_absolute_fallback_disabled_by_default() {
+        let m = match_filename("Show - 045.mkv", None, false);
+        assert_eq!(m.episode_absolute, None);
+    }
+
+    #[test]
+    fn test_year_extraction() {
+        let m = match_filename("Movie.Title.2023.1080p.BluRay.x264.mkv", None, false);
+        assert_eq!(m.title, "Movie Title");
+        assert_eq!(m.year, Some(2023));
+    }
+
+    #[test]
+    fn test_year_not_confused_with_resolution() {
+        // Neither the episode tag nor the 2160p resolution tag is read as a year.
+        let m = match_filename("Show.Name.S01E02.2160p.mkv", None, false);
+        assert_eq!(m.year, None);
+    }
+
+    #[test]
+    fn test_season_folder() {
+        assert_eq!(match_season_folder("Season 02"), Some(2));
+        assert_eq!(match_season_folder("S03"), Some(3));
+        assert_eq!(match_season_folder("Extras"), None);
+    }
+
+    #[test]
+    fn test_release_group_bracket_stripped() {
+        let m = match_filename("[ReleaseGroup] Show Name - 12 [1080p].mkv", None, true);
+        assert_eq!(m.title, "Show Name - 12");
+        assert_eq!(m.episode_absolute, Some(12));
+    }
+
+    #[test]
+    fn test_clutter_name_builtin_patterns() {
+        assert!(is_clutter_name("Movie.Title.Sample.mkv", &[]));
+        assert!(is_clutter_name("Movie-trailer.mp4", &[]));
+        assert!(is_clutter_name("Deleted.Scenes", &[]));
+        assert!(is_clutter_name("Behind the Scenes.mkv", &[]));
+        assert!(!is_clutter_name("Movie Title.mkv", &[]));
+    }
+
+    #[test]
+    fn test_clutter_name_extra_patterns() {
+        let extra = vec!["bonus".to_string()];
+        assert!(is_clutter_name("Bonus.Content.mkv", &extra));
+        assert!(!is_clutter_name("Bonus.Content.mkv", &[]));
+    }
+
+    #[test]
+    fn test_is_clutter_by_size() {
+        assert!(is_clutter("Movie Title.mkv", 1024, &[]));
+        assert!(!is_clutter("Movie Title.mkv", CLUTTER_MIN_SIZE_BYTES, &[]));
+    }
+}
@@ -1,12 +1,211 @@
 use anyhow::{Context, Result};
+use futures_util::future::{FutureExt, Shared};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
-use std::sync::OnceLock;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 
-#[derive(Debug, Serialize, Deserialize)]
+const TMDB_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+/// Token-bucket capacity/refill rate, tuned to stay under TMDB's
+/// documented ~50 requests/10s limit with some headroom.
+const TMDB_BUCKET_CAPACITY: f64 = 40.0;
+const TMDB_REFILL_PER_SEC: f64 = 4.0;
+const TMDB_MAX_RETRIES: u32 = 4;
+
+type TmdbRawResponse = (u16, Vec<u8>);
+type TmdbSharedFuture =
+    Shared<Pin<Box<dyn Future<Output = Arc<Result<TmdbRawResponse, String>>> + Send>>>;
+
+/// Client-side throttle so a scanner walking a large library doesn't blow
+/// through TMDB's rate limit and spend its time getting 429'd. Refills
+/// continuously rather than in fixed windows, so `try_acquire` never has to
+/// wait a full window for a single token.
+struct TokenBucket {
+    tokens: f64,
+    capacity: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            tokens: capacity,
+            capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Refills for elapsed time, then either takes a token (returning
+    /// `None`) or reports how long the caller must wait for one.
+    fn try_acquire(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            let deficit = 1.0 - self.tokens;
+            Some(Duration::from_secs_f64(deficit / self.refill_per_sec))
+        }
+    }
+}
+
+/// Shared HTTP client for every TMDB fetcher in this module: one
+/// `reqwest::Client`, a client-side rate limiter, and an in-flight request
+/// deduplicator so two files resolving to the same search/lookup URL share
+/// a single HTTP round trip instead of firing it twice.
+pub struct TmdbClient {
+    http: reqwest::Client,
+    bucket: Mutex<TokenBucket>,
+    /// Keyed by request URL. `Weak` so a finished (and no-longer-awaited)
+    /// request doesn't keep its entry alive forever; the entry is replaced
+    /// the next time that URL is requested and nobody else is still
+    /// holding the strong `Arc`.
+    inflight: Mutex<HashMap<String, Weak<TmdbSharedFuture>>>,
+}
+
+impl TmdbClient {
+    fn global() -> &'static TmdbClient {
+        static CLIENT: OnceLock<TmdbClient> = OnceLock::new();
+        CLIENT.get_or_init(|| TmdbClient {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(120))
+                .build()
+                .expect("failed to build TMDB HTTP client"),
+            bucket: Mutex::new(TokenBucket::new(TMDB_BUCKET_CAPACITY, TMDB_REFILL_PER_SEC)),
+            inflight: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn acquire(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap().try_acquire();
+            match wait {
+                None => return,
+                Some(d) => tokio::time::sleep(d).await,
+            }
+        }
+    }
+
+    /// Sends one GET, retrying on `429 Too Many Requests` for up to
+    /// `TMDB_MAX_RETRIES` attempts in all, honoring `Retry-After` when present
+    /// and otherwise backing off exponentially. A 429 on the last attempt is
+    /// returned to the caller like any other response.
+    async fn send_with_retry(
+        &self,
+        url: String,
+        token: Option<String>,
+    ) -> Arc<Result<TmdbRawResponse, String>> {
+        let mut backoff = Duration::from_millis(500);
+
+        for attempt in 0..TMDB_MAX_RETRIES {
+            self.acquire().await;
+
+            let mut req = self
+                .http
+                .get(&url)
+                .header("Accept", "application/json")
+                .header("User-Agent", TMDB_USER_AGENT);
+            if let Some(token) = &token
+                && !token.is_empty()
+            {
+                req = req.header("Authorization", format!("Bearer {}", token));
+            }
+
+            let resp = match req.send().await {
+                Ok(r) => r,
+                Err(e) => return Arc::new(Err(e.to_string())),
+            };
+
+            let status = resp.status();
+            if status == reqwest::StatusCode::TOO_MANY_REQUESTS && attempt + 1 < TMDB_MAX_RETRIES {
+                let wait = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(backoff);
+                println!("[metadata] TMDB 429, retrying {} after {:?}", url, wait);
+                tokio::time::sleep(wait).await;
+                backoff *= 2;
+                continue;
+            }
+
+            return match resp.bytes().await {
+                Ok(bytes) => Arc::new(Ok((status.as_u16(), bytes.to_vec()))),
+                Err(e) => Arc::new(Err(e.to_string())),
+            };
+        }
+
+        Arc::new(Err(format!(
+            "TMDB request to {} exhausted retries after repeated 429s",
+            url
+        )))
+    }
+
+    /// Fetches `url`, coalescing with any other in-flight request for the
+    /// same URL so concurrent callers share one HTTP round trip.
+    async fn fetch(
+        &'static self,
+        url: String,
+        token: Option<String>,
+    ) -> Arc<Result<TmdbRawResponse, String>> {
+        let shared_arc: Arc<TmdbSharedFuture> = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&url).and_then(Weak::upgrade) {
+                Some(existing) => existing,
+                None => {
+                    let fut: Pin<
+                        Box<dyn Future<Output = Arc<Result<TmdbRawResponse, String>>> + Send>,
+                    > = Box::pin(self.send_with_retry(url.clone(), token));
+                    let arc = Arc::new(fut.shared());
+                    inflight.insert(url.clone(), Arc::downgrade(&arc));
+                    arc
+                }
+            }
+        };
+
+        let cloned = (*shared_arc).clone();
+        let result = cloned.await;
+        drop(shared_arc);
+        result
+    }
+
+    /// Convenience wrapper for callers that just want the status/body pair
+    /// as an `anyhow::Result`, without juggling the internal `String` error
+    /// type the shared future needs to stay `Clone`.
+    async fn get(&'static self, url: String, token: Option<String>) -> Result<TmdbRawResponse> {
+        match &*self.fetch(url, token).await {
+            Ok(pair) => Ok(pair.clone()),
+            Err(e) => Err(anyhow::anyhow!("{}", e)),
+        }
+    }
+}
+
+/// TMDB auth token to send: the configured access token, falling back to
+/// the crate's bundled default so the app works out of the box.
+fn tmdb_token(config: &crate::models::AppConfig) -> Option<String> {
+    let token = if !config.tmdb_access_token.is_empty() {
+        &config.tmdb_access_token
+    } else {
+        crate::models::DEFAULT_TMDB_ACCESS_TOKEN
+    };
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LocalMetadata {
     pub title: String,
     pub overview: String,
@@ -14,6 +213,80 @@ pub struct LocalMetadata {
     pub tmdb_id: u64,
     #[serde(default)]
     pub episode_number: Option<u32>,
+    /// Filled in separately by `enrich_technical_metadata`, since it comes
+    /// from probing the actual file rather than TMDB.
+    #[serde(default)]
+    pub technical: Option<TechnicalMetadata>,
+    /// BlurHash placeholder for `poster_path`'s downloaded image, computed
+    /// once right after the download (see `download_poster_with_blurhash`).
+    #[serde(default)]
+    pub blurhash: Option<String>,
+    /// TMDB's `original_title`/`original_name`, independent of
+    /// `AppConfig::metadata_language`. Filled in when the English fallback
+    /// lookup runs (or when TMDB already returned it directly).
+    #[serde(default)]
+    pub original_title: Option<String>,
+    /// Which `classify_media_kind` signal (if any) promoted this file to a
+    /// TV lookup despite its library not being flagged `TVShows` -- `None`
+    /// when the library's own `kind` already matched. Surfaced so the UI
+    /// can show why a file was matched the way it was.
+    #[serde(default)]
+    pub classification_signal: Option<ClassificationSignal>,
+}
+
+/// Technical detail ffprobe can see but TMDB has no idea about: resolution,
+/// codecs, duration, and per-track languages.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TechnicalMetadata {
+    pub duration_secs: Option<f64>,
+    pub video_codec: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fps: Option<f64>,
+    #[serde(default)]
+    pub audio_tracks: Vec<TechnicalTrack>,
+    #[serde(default)]
+    pub subtitle_tracks: Vec<TechnicalTrack>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TechnicalTrack {
+    pub codec: String,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FFProbeFormat {
+    #[serde(default)]
+    duration: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct FFProbeStreamTags {
+    #[serde(default)]
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FFProbeStream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    #[serde(default)]
+    width: Option<u32>,
+    #[serde(default)]
+    height: Option<u32>,
+    #[serde(default)]
+    r_frame_rate: Option<String>,
+    #[serde(default)]
+    tags: Option<FFProbeStreamTags>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct FFProbeOutput {
+    #[serde(default)]
+    format: Option<FFProbeFormat>,
+    #[serde(default)]
+    streams: Option<Vec<FFProbeStream>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,6 +301,14 @@ struct TmdbResult {
     title: String,
     overview: String,
     poster_path: Option<String>,
+    #[serde(alias = "first_air_date", default)]
+    release_date: Option<String>,
+    #[serde(default)]
+    popularity: f64,
+    #[serde(default)]
+    vote_count: u64,
+    #[serde(alias = "original_name", default)]
+    original_title: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,6 +317,17 @@ struct TmdbSeasonResponse {
     name: String,
     overview: String,
     poster_path: Option<String>,
+    /// Only used for its length, by `resolve_absolute_episode` -- TMDB's
+    /// season detail endpoint is the only place that exposes "how many
+    /// episodes does this season have" without fetching every episode.
+    #[serde(default)]
+    episodes: Vec<TmdbSeasonEpisodeStub>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmdbSeasonEpisodeStub {
+    #[allow(dead_code)]
+    episode_number: u32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,7 +378,295 @@ pub async fn save_local_metadata(path: &Path, metadata: &LocalMetadata) -> Resul
     }
 }
 
-pub fn cleanup_filename(filename: &str) -> (String, Option<String>) {
+/// Runs ffprobe against `path` and merges the result into whatever local
+/// metadata was already saved for it. Gated on `AppConfig::probe_media_info`
+/// since it's an extra subprocess per file. Failures are logged and
+/// swallowed: a file ffprobe can't parse shouldn't fail the scan task that
+/// already successfully saved descriptive (TMDB) metadata.
+pub async fn enrich_technical_metadata(path: &Path, config: &crate::models::AppConfig) {
+    if !config.probe_media_info {
+        return;
+    }
+
+    let Some(mut meta) = read_local_metadata(path).await else {
+        return;
+    };
+
+    match probe_technical_metadata(path).await {
+        Some(tech) => {
+            meta.technical = Some(tech);
+            if let Err(e) = save_local_metadata(path, &meta).await {
+                eprintln!(
+                    "[metadata] Failed to save technical metadata for {:?}: {}",
+                    path, e
+                );
+            }
+        }
+        None => {
+            println!(
+                "[metadata] No usable ffprobe streams for {:?}; skipping technical metadata",
+                path
+            );
+        }
+    }
+}
+
+/// Probes `path` with ffprobe for resolution/codec/duration/track-language
+/// detail. Returns `None` (without erroring) for anything that doesn't
+/// parse as valid media, so one bad file can't fail a whole scan task.
+pub async fn probe_technical_metadata(path: &Path) -> Option<TechnicalMetadata> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let probe: FFProbeOutput = serde_json::from_slice(&output.stdout).ok()?;
+    let streams = probe.streams.unwrap_or_default();
+    if streams.is_empty() {
+        return None;
+    }
+
+    let video = streams
+        .iter()
+        .find(|s| s.codec_type.as_deref() == Some("video"));
+    let fps = video
+        .and_then(|s| s.r_frame_rate.as_deref())
+        .and_then(parse_frame_rate);
+
+    let track_of = |kind: &str| -> Vec<TechnicalTrack> {
+        streams
+            .iter()
+            .filter(|s| s.codec_type.as_deref() == Some(kind))
+            .map(|s| TechnicalTrack {
+                codec: s
+                    .codec_name
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string()),
+                language: s.tags.as_ref().and_then(|t| t.language.clone()),
+            })
+            .collect()
+    };
+
+    Some(TechnicalMetadata {
+        duration_secs: probe
+            .format
+            .and_then(|f| f.duration)
+            .and_then(|d| d.parse().ok()),
+        video_codec: video.and_then(|s| s.codec_name.clone()),
+        width: video.and_then(|s| s.width),
+        height: video.and_then(|s| s.height),
+        fps,
+        audio_tracks: track_of("audio"),
+        subtitle_tracks: track_of("subtitle"),
+    })
+}
+
+/// Parses ffprobe's `r_frame_rate` (e.g. `"24000/1001"`) into a plain fps.
+fn parse_frame_rate(raw: &str) -> Option<f64> {
+    let mut parts = raw.split('/');
+    let num: f64 = parts.next()?.parse().ok()?;
+    let den: f64 = parts.next().unwrap_or("1").parse().ok()?;
+    if den == 0.0 {
+        return None;
+    }
+    Some(num / den)
+}
+
+/// Video resolution tag parsed out of a release filename.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseResolution {
+    /// `2160p`/`4k`
+    Uhd,
+    /// `1080p`
+    Fhd,
+    /// `720p`
+    Hd,
+}
+
+/// Encode/distribution source tag parsed out of a release filename.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseSource {
+    Remux,
+    BluRay,
+    WebDl,
+    WebRip,
+    Hdtv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoCodec {
+    H264,
+    /// Covers both `x265` and `hevc` tags -- the same codec.
+    H265,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioCodec {
+    Aac,
+    Ac3,
+    Dts,
+}
+
+/// Release-quality classification parsed out of a filename by
+/// `cleanup_filename`, alongside (not replacing) the cleaned title/year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReleaseInfo {
+    pub resolution: Option<ReleaseResolution>,
+    pub source: Option<ReleaseSource>,
+    pub video_codec: Option<VideoCodec>,
+    pub audio_codec: Option<AudioCodec>,
+    /// Whether the filename matched a known cam/telesync-style pirated
+    /// source tag (`CAM`, `TS`, `TELESYNC`, ...).
+    pub is_cam: bool,
+}
+
+impl ReleaseInfo {
+    /// A rough, higher-is-better quality score: resolution and source each
+    /// contribute a weighted bonus, with a heavy penalty for `is_cam` so a
+    /// cam-sourced 1080p release still scores far below a legitimate 720p
+    /// one.
+    pub fn quality_score(&self) -> i32 {
+        let resolution_score = match self.resolution {
+            Some(ReleaseResolution::Uhd) => 40,
+            Some(ReleaseResolution::Fhd) => 30,
+            Some(ReleaseResolution::Hd) => 20,
+            None => 0,
+        };
+        let source_score = match self.source {
+            Some(ReleaseSource::Remux) => 35,
+            Some(ReleaseSource::BluRay) => 30,
+            Some(ReleaseSource::WebDl) => 20,
+            Some(ReleaseSource::WebRip) => 15,
+            Some(ReleaseSource::Hdtv) => 10,
+            None => 0,
+        };
+
+        let mut score = resolution_score + source_score;
+        if self.is_cam {
+            score -= 1000;
+        }
+        score
+    }
+}
+
+/// Release tokens that identify a pirated cam/telesync source rather than a
+/// legitimate rip, matched as whole words after normalizing non-word
+/// characters to spaces.
+const CAM_SOURCE_TOKENS: &[&str] = &[
+    "cam",
+    "camrip",
+    "hdcam",
+    "ts",
+    "tsrip",
+    "hdts",
+    "telesync",
+    "pdvd",
+    "tc",
+    "hdtc",
+    "telecine",
+    "workprint",
+];
+
+/// Splits `filename` into lowercased whole-word tokens, with every
+/// non-alphanumeric run (dots, underscores, dashes, brackets) collapsed to
+/// a single separator -- so e.g. `Blu-Ray` and `BluRay` both tokenize
+/// consistently.
+fn release_tokens(filename: &str) -> Vec<String> {
+    static NON_WORD_RE: OnceLock<Regex> = OnceLock::new();
+    let non_word_re = NON_WORD_RE.get_or_init(|| Regex::new(r"[^A-Za-z0-9]+").unwrap());
+    non_word_re
+        .replace_all(filename, " ")
+        .to_lowercase()
+        .split_whitespace()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// True when `tokens` contains `phrase` as a contiguous run of whole words
+/// (so `has_phrase(tokens, "web dl")` matches both `WEB-DL` and `WEB.DL`,
+/// already tokenized the same way by `release_tokens`).
+fn has_phrase(tokens: &[String], phrase: &str) -> bool {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    tokens
+        .windows(words.len())
+        .any(|w| w.iter().map(String::as_str).eq(words.iter().copied()))
+}
+
+/// Parses the release-quality tags out of `filename`: resolution, source,
+/// codecs, and whether it looks like a cam/telesync (pirated) release.
+pub fn parse_release_info(filename: &str) -> ReleaseInfo {
+    let tokens = release_tokens(filename);
+    let has = |tag: &str| tokens.iter().any(|t| t == tag);
+
+    let resolution = if has("2160p") || has("4k") {
+        Some(ReleaseResolution::Uhd)
+    } else if has("1080p") {
+        Some(ReleaseResolution::Fhd)
+    } else if has("720p") {
+        Some(ReleaseResolution::Hd)
+    } else {
+        None
+    };
+
+    let source = if has("remux") {
+        Some(ReleaseSource::Remux)
+    } else if has("bluray") || has_phrase(&tokens, "blu ray") {
+        Some(ReleaseSource::BluRay)
+    } else if has("webdl") || has_phrase(&tokens, "web dl") {
+        Some(ReleaseSource::WebDl)
+    } else if has("webrip") || has_phrase(&tokens, "web rip") {
+        Some(ReleaseSource::WebRip)
+    } else if has("hdtv") {
+        Some(ReleaseSource::Hdtv)
+    } else {
+        None
+    };
+
+    let video_codec = if has("x265") || has("hevc") {
+        Some(VideoCodec::H265)
+    } else if has("x264") {
+        Some(VideoCodec::H264)
+    } else {
+        None
+    };
+
+    let audio_codec = if has("aac") {
+        Some(AudioCodec::Aac)
+    } else if has("ac3") {
+        Some(AudioCodec::Ac3)
+    } else if has("dts") {
+        Some(AudioCodec::Dts)
+    } else {
+        None
+    };
+
+    let is_cam = tokens
+        .iter()
+        .any(|t| CAM_SOURCE_TOKENS.contains(&t.as_str()));
+
+    ReleaseInfo {
+        resolution,
+        source,
+        video_codec,
+        audio_codec,
+        is_cam,
+    }
+}
+
+pub fn cleanup_filename(filename: &str) -> (String, Option<String>, ReleaseInfo) {
     // 1. Find the year (19xx or 20xx)
     static YEAR_RE: OnceLock<Regex> = OnceLock::new();
     let year_re = YEAR_RE.get_or_init(|| Regex::new(r"[\(\[\.]*(19|20)\d{2}[\)\]\.]*").unwrap());
@@ -96,9 +676,7 @@ pub fn cleanup_filename(filename: &str) -> (String, Option<String>) {
         let raw_year = mat.as_str();
         static CLEAN_YEAR_RE: OnceLock<Regex> = OnceLock::new();
         let clean_year_re = CLEAN_YEAR_RE.get_or_init(|| Regex::new(r"\d{4}").unwrap());
-        let year_val = clean_year_re
-            .find(raw_year)
-            .map(|m| m.as_str().to_string());
+        let year_val = clean_year_re.find(raw_year).map(|m| m.as_str().to_string());
 
         // Keep everything up to the START of the year match for title
         let start = mat.start();
@@ -122,133 +700,516 @@ pub fn cleanup_filename(filename: &str) -> (String, Option<String>) {
     let space_re = SPACE_RE.get_or_init(|| Regex::new(r"\s+").unwrap());
     let final_title = space_re.replace_all(&clean, " ").trim().to_string();
 
-    (final_title, year)
+    (final_title, year, parse_release_info(filename))
 }
 
-pub async fn fetch_tmdb_metadata(
+/// Season/episode markers `parse_filename` can recognize, in the priority
+/// order a release-group filename would use them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFilename {
+    pub title: String,
+    pub year: Option<String>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    /// Set only when no season/episode marker was found at all -- a bare
+    /// 1-3 digit number, used by absolute-numbered anime-style releases.
+    pub absolute_episode: Option<u32>,
+}
+
+/// `cleanup_filename`'s TV-aware counterpart: in addition to title/year,
+/// scans tokens for a season/episode marker, trying each form in turn --
+/// `S01E02`, then `1x02`, then a bare `S01` (season only), then the words
+/// "Season"/"Episode" -- and falling back to a bare 1-3 digit group as an
+/// absolute episode number only when none of those matched and the name
+/// doesn't also contain something that looks like a year. Quality/source
+/// tags are stripped first so `1080p`/`720p` can never be misread as an
+/// episode number.
+pub fn parse_filename(filename: &str) -> ParsedFilename {
+    static TAGS_RE: OnceLock<Regex> = OnceLock::new();
+    let tags_re = TAGS_RE.get_or_init(|| {
+        Regex::new(r"(?i)[\s\.]*(1080p|720p|4k|2160p|bluray|web-dl|webrip|remux|hdr|x264|x265|hevc|aac|ac3|dts|eng|sub|subs)[\s\.]*").unwrap()
+    });
+    let de_tagged = tags_re.replace_all(filename, " ").into_owned();
+
+    static SXE_RE: OnceLock<Regex> = OnceLock::new();
+    let sxe_re = SXE_RE.get_or_init(|| Regex::new(r"(?i)s(\d{1,2})\s?e(\d{1,3})").unwrap());
+    static ALT_X_RE: OnceLock<Regex> = OnceLock::new();
+    let alt_x_re = ALT_X_RE.get_or_init(|| Regex::new(r"(?i)\b(\d{1,2})x(\d{1,3})\b").unwrap());
+    static SEASON_ONLY_RE: OnceLock<Regex> = OnceLock::new();
+    let season_only_re = SEASON_ONLY_RE.get_or_init(|| Regex::new(r"(?i)\bs(\d{1,2})\b").unwrap());
+    static SEASON_WORD_RE: OnceLock<Regex> = OnceLock::new();
+    let season_word_re =
+        SEASON_WORD_RE.get_or_init(|| Regex::new(r"(?i)\bseason\s+(\d{1,2})\b").unwrap());
+    static EPISODE_WORD_RE: OnceLock<Regex> = OnceLock::new();
+    let episode_word_re =
+        EPISODE_WORD_RE.get_or_init(|| Regex::new(r"(?i)\bepisode\s+(\d{1,3})\b").unwrap());
+    static YEAR_RE: OnceLock<Regex> = OnceLock::new();
+    let year_re = YEAR_RE.get_or_init(|| Regex::new(r"[\(\[\.]*(19|20)\d{2}[\)\]\.]*").unwrap());
+    static CLEAN_YEAR_RE: OnceLock<Regex> = OnceLock::new();
+    let clean_year_re = CLEAN_YEAR_RE.get_or_init(|| Regex::new(r"\d{4}").unwrap());
+    static BARE_NUM_RE: OnceLock<Regex> = OnceLock::new();
+    let bare_num_re = BARE_NUM_RE.get_or_init(|| Regex::new(r"\b(\d{1,3})\b").unwrap());
+
+    let mut season = None;
+    let mut episode = None;
+    let mut absolute_episode = None;
+    let mut marker_start: Option<usize> = None;
+
+    if let Some(caps) = sxe_re.captures(&de_tagged) {
+        season = caps[1].parse().ok();
+        episode = caps[2].parse().ok();
+        marker_start = Some(caps.get(0).unwrap().start());
+    } else if let Some(caps) = alt_x_re.captures(&de_tagged) {
+        season = caps[1].parse().ok();
+        episode = caps[2].parse().ok();
+        marker_start = Some(caps.get(0).unwrap().start());
+    } else if let Some(caps) = season_only_re.captures(&de_tagged) {
+        season = caps[1].parse().ok();
+        marker_start = Some(caps.get(0).unwrap().start());
+    } else {
+        let season_caps = season_word_re.captures(&de_tagged);
+        let episode_caps = episode_word_re.captures(&de_tagged);
+        let mut starts = Vec::new();
+        if let Some(caps) = &season_caps {
+            season = caps[1].parse().ok();
+            starts.push(caps.get(0).unwrap().start());
+        }
+        if let Some(caps) = &episode_caps {
+            episode = caps[1].parse().ok();
+            starts.push(caps.get(0).unwrap().start());
+        }
+        marker_start = starts.into_iter().min();
+    }
+
+    let year_match = year_re.find(&de_tagged);
+
+    if season.is_none()
+        && episode.is_none()
+        && year_match.is_none()
+        && let Some(m) = bare_num_re.find(&de_tagged)
+    {
+        absolute_episode = m.as_str().parse().ok();
+        marker_start = Some(m.start());
+    }
+
+    let year = year_match.and_then(|m| {
+        clean_year_re
+            .find(m.as_str())
+            .map(|y| y.as_str().to_string())
+    });
+
+    let title_end = match (marker_start, year_match.map(|m| m.start())) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (Some(a), None) => Some(a),
+        (None, Some(b)) => Some(b),
+        (None, None) => None,
+    };
+
+    let raw_title = match title_end {
+        Some(end) => &de_tagged[..end],
+        None => &de_tagged[..],
+    };
+
+    let clean = raw_title.replace(['.', '_', '(', ')', '[', ']'], " ");
+    static SPACE_RE: OnceLock<Regex> = OnceLock::new();
+    let space_re = SPACE_RE.get_or_init(|| Regex::new(r"\s+").unwrap());
+    let title = space_re.replace_all(&clean, " ").trim().to_string();
+
+    ParsedFilename {
+        title,
+        year,
+        season,
+        episode,
+        absolute_episode,
+    }
+}
+
+/// Which signal `classify_media_kind` based its TV/movie decision on --
+/// surfaced so callers (and eventually the UI) can show why a file landed
+/// where it did instead of just the final bool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ClassificationSignal {
+    /// A season/episode or absolute-episode marker parsed (`parse_filename`).
+    EpisodeNumber,
+    /// A daily-show air date parsed (`YYYY.MM.DD` / `YYYY-MM-DD`).
+    AirDate,
+    /// Neither parsed, but a 19xx/20xx year (bare, dotted, or in
+    /// parens/brackets) did.
+    Year,
+    /// Nothing useful parsed at all.
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaClassification {
+    pub is_tv: bool,
+    pub signal: ClassificationSignal,
+    /// The filename prefix left after stripping whichever token `signal`
+    /// matched -- the detected series (or movie) name.
+    pub series_name: String,
+}
+
+/// Content-based TV-vs-movie classifier, for mixed libraries and mislabelled
+/// files that `library.kind` alone gets wrong. Tries, in order: (a) an
+/// episode-number parse via `parse_filename` (`SxxExx`, `SxEx`, `1x02`, or a
+/// bare absolute number); (b) a `YYYY.MM.DD`/`YYYY-MM-DD` air date, as used
+/// by daily shows; (c) a 19xx/20xx year (bare, dotted, or in
+/// parens/brackets), which without an episode
+/// or date marker reads as a movie. An episode number or date always wins
+/// over a year -- a plausible series name and a movie title can both appear
+/// in the same string (`Show.Name.2024.S01E02...`), and per the filebot AMC
+/// flow, the episode/date token is what actually decides it.
+pub fn classify_media_kind(filename: &str) -> MediaClassification {
+    static AIR_DATE_RE: OnceLock<Regex> = OnceLock::new();
+    let air_date_re = AIR_DATE_RE
+        .get_or_init(|| Regex::new(r"\b((?:19|20)\d{2})[.\-](\d{2})[.\-](\d{2})\b").unwrap());
+
+    let parsed = parse_filename(filename);
+    if parsed.season.is_some() || parsed.episode.is_some() || parsed.absolute_episode.is_some() {
+        return MediaClassification {
+            is_tv: true,
+            signal: ClassificationSignal::EpisodeNumber,
+            series_name: parsed.title,
+        };
+    }
+
+    if let Some(caps) = air_date_re.captures(filename) {
+        let date_match = caps.get(0).unwrap();
+        let series_name = clean_title_prefix(&filename[..date_match.start()]);
+        return MediaClassification {
+            is_tv: true,
+            signal: ClassificationSignal::AirDate,
+            series_name,
+        };
+    }
+
+    if parsed.year.is_some() {
+        return MediaClassification {
+            is_tv: false,
+            signal: ClassificationSignal::Year,
+            series_name: parsed.title,
+        };
+    }
+
+    MediaClassification {
+        is_tv: false,
+        signal: ClassificationSignal::None,
+        series_name: parsed.title,
+    }
+}
+
+/// Strips trailing separators/junk left over after slicing a filename
+/// prefix off at a marker's start position, same cleanup `parse_filename`
+/// applies to its own title.
+fn clean_title_prefix(raw: &str) -> String {
+    let clean = raw.replace(['.', '_', '(', ')', '[', ']'], " ");
+    static SPACE_RE: OnceLock<Regex> = OnceLock::new();
+    let space_re = SPACE_RE.get_or_init(|| Regex::new(r"\s+").unwrap());
+    space_re.replace_all(&clean, " ").trim().to_string()
+}
+
+/// Anime-release filename tokenizer, for names like `[SubGroup] Show Name -
+/// 012 (1080p) [ABCD1234].mkv` that `parse_filename`'s `SxxExx`-oriented
+/// regexes don't recognize at all. Repeatedly strips leading/trailing
+/// `[...]`/`(...)` groups -- fansub tag, resolution, CRC -- as release
+/// metadata rather than title, then pulls the absolute episode number out
+/// of the remaining ` - NNN` suffix.
+pub fn parse_anime_filename(filename: &str) -> (String, Option<u32>) {
+    let stem = Path::new(filename)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| filename.to_string());
+
+    static LEADING_GROUP_RE: OnceLock<Regex> = OnceLock::new();
+    let leading_group_re =
+        LEADING_GROUP_RE.get_or_init(|| Regex::new(r"^\s*[\[(][^\])]*[\])]\s*").unwrap());
+    static TRAILING_GROUP_RE: OnceLock<Regex> = OnceLock::new();
+    let trailing_group_re =
+        TRAILING_GROUP_RE.get_or_init(|| Regex::new(r"\s*[\[(][^\])]*[\])]\s*$").unwrap());
+
+    let mut working = stem;
+    loop {
+        let stripped = leading_group_re.replace(&working, "").into_owned();
+        if stripped == working {
+            break;
+        }
+        working = stripped;
+    }
+    loop {
+        let stripped = trailing_group_re.replace(&working, "").into_owned();
+        if stripped == working {
+            break;
+        }
+        working = stripped;
+    }
+
+    static ABS_EP_RE: OnceLock<Regex> = OnceLock::new();
+    let abs_ep_re = ABS_EP_RE.get_or_init(|| Regex::new(r"-\s*(\d{1,4})\s*$").unwrap());
+
+    match abs_ep_re.captures(&working) {
+        Some(caps) => {
+            let marker = caps.get(0).unwrap();
+            let episode = caps[1].parse().ok();
+            (working[..marker.start()].trim().to_string(), episode)
+        }
+        None => (working.trim().to_string(), None),
+    }
+}
+
+/// Lowercases and collapses punctuation/whitespace so two titles that only
+/// differ by casing, an apostrophe, or a colon still compare as equal.
+fn normalize_for_match(s: &str) -> String {
+    static PUNCT_RE: OnceLock<Regex> = OnceLock::new();
+    let punct_re = PUNCT_RE.get_or_init(|| Regex::new(r"[^\w\s]").unwrap());
+    static SPACE_RE: OnceLock<Regex> = OnceLock::new();
+    let space_re = SPACE_RE.get_or_init(|| Regex::new(r"\s+").unwrap());
+
+    let lower = s.to_lowercase();
+    let no_punct = punct_re.replace_all(&lower, " ");
+    space_re.replace_all(&no_punct, " ").trim().to_string()
+}
+
+/// Classic edit-distance DP, one row at a time.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0usize; b.len() + 1];
+
+    for (i, &ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j + 1] + 1).min(curr[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Normalized title similarity in `[0.0, 1.0]`: `1 - levenshtein / max_len`
+/// over the normalized (lowercased, punctuation-stripped) titles.
+fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = normalize_for_match(a);
+    let b = normalize_for_match(b);
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - (levenshtein(&a, &b) as f64 / max_len as f64)
+}
+
+/// Scores a TMDB search `result` against the parsed `query`/`year` so
+/// `fetch_tmdb_metadata` can pick the best candidate instead of trusting
+/// TMDB's result ordering: title similarity, a flat bonus when the
+/// candidate's release year matches, and a small popularity/vote-count
+/// tie-breaker that's kept too small to ever outweigh a title/year match.
+fn score_tmdb_match(result: &TmdbResult, query: &str, year: Option<&str>) -> f64 {
+    let mut score = title_similarity(&result.title, query);
+
+    if let (Some(wanted), Some(release)) = (year, result.release_date.as_deref())
+        && release.get(0..4) == Some(wanted)
+    {
+        score += 0.3;
+    }
+
+    let popularity_bonus = (result.popularity.max(0.0) / 1000.0).min(0.02);
+    let vote_bonus = ((result.vote_count as f64).ln_1p() / 1000.0).min(0.02);
+    score + popularity_bonus + vote_bonus
+}
+
+/// Fallback language re-requested when `AppConfig::metadata_language`'s
+/// localized response comes back with an empty overview.
+const ENGLISH_FALLBACK_LANGUAGE: &str = "en-US";
+
+/// Runs one `/search/{endpoint}` call at the given TMDB `language` and
+/// returns its raw results, shared by `fetch_tmdb_metadata`'s primary
+/// lookup and its English fallback.
+async fn search_tmdb_results(
     config: &crate::models::AppConfig,
+    endpoint: &str,
     query: &str,
     year: Option<&str>,
-    is_tv: bool,
-) -> Result<Option<LocalMetadata>> {
-    let client = reqwest::Client::new();
-    let endpoint = if is_tv { "tv" } else { "movie" };
-    let year_param = if is_tv { "first_air_date_year" } else { "year" };
+    language: &str,
+) -> Result<Vec<TmdbResult>> {
+    let year_param = if endpoint == "tv" {
+        "first_air_date_year"
+    } else {
+        "year"
+    };
 
     let mut url = format!(
-        "{}/search/{}?query={}&language=en-US&page=1&include_adult=false",
+        "{}/search/{}?query={}&language={}&page=1&include_adult=false",
         config.tmdb_base_url,
         endpoint,
-        urlencoding::encode(query)
+        urlencoding::encode(query),
+        language
     );
-
     if let Some(y) = year {
         url.push_str(&format!("&{}={}", year_param, y));
     }
 
-    println!(
-        "[metadata] Searching TMDB ({}) for: '{}' (Year: {:?})",
-        endpoint, query, year
-    );
     println!("[metadata] Request URL: {}", url);
-    println!("[metadata] Request Headers: Accept: application/json");
-
-    let mut req = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
-
-    let token = if !config.tmdb_access_token.is_empty() {
-        &config.tmdb_access_token
-    } else {
-        crate::models::DEFAULT_TMDB_ACCESS_TOKEN
-    };
-
-    if !token.is_empty() {
-        req = req.header("Authorization", format!("Bearer {}", token));
-    }
 
-    let resp = req.send().await.context("Failed to send TMDB request")?;
+    let (status, body) = TmdbClient::global()
+        .get(url, tmdb_token(config))
+        .await
+        .context("Failed to send TMDB request")?;
 
-    println!("[metadata] Response status: {}", resp.status());
+    println!("[metadata] Response status: {}", status);
 
-    if !resp.status().is_success() {
-        let error_text = resp.text().await.unwrap_or_else(|_| "Unknown error".into());
+    if status < 200 || status >= 300 {
+        let error_text = String::from_utf8_lossy(&body);
         println!("[metadata] API Error Body: {}", error_text);
         return Err(anyhow::anyhow!("TMDB API Error"));
     }
 
     let search_res: TmdbSearchResponse =
-        resp.json().await.context("Failed to parse TMDB response")?;
+        serde_json::from_slice(&body).context("Failed to parse TMDB response")?;
+    Ok(search_res.results)
+}
+
+pub async fn fetch_tmdb_metadata(
+    config: &crate::models::AppConfig,
+    query: &str,
+    year: Option<&str>,
+    is_tv: bool,
+) -> Result<Option<LocalMetadata>> {
+    let endpoint = if is_tv { "tv" } else { "movie" };
+
+    println!(
+        "[metadata] Searching TMDB ({}) for: '{}' (Year: {:?})",
+        endpoint, query, year
+    );
+
+    let results =
+        search_tmdb_results(config, endpoint, query, year, &config.metadata_language).await?;
+    println!("[metadata] Found {} results", results.len());
+
+    let best = results
+        .into_iter()
+        .map(|result| {
+            let score = score_tmdb_match(&result, query, year);
+            (score, result)
+        })
+        .max_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let Some((score, mut movie)) = best else {
+        return Ok(None);
+    };
 
-    println!("[metadata] Found {} results", search_res.results.len());
-    if let Some(first) = search_res.results.first() {
-        println!("[metadata] Top match: {} ({})", first.title, first.id);
+    if score < config.metadata_match_threshold {
+        tracing::debug!(
+            "[metadata] Best candidate for '{}' ({}) scored {:.3}, below threshold {:.3}",
+            query,
+            movie.title,
+            score,
+            config.metadata_match_threshold
+        );
+        return Ok(None);
     }
+    tracing::debug!(
+        "[metadata] Best match for '{}': {} ({}), score={:.3}",
+        query,
+        movie.title,
+        movie.id,
+        score
+    );
 
-    if let Some(movie) = search_res.results.into_iter().next() {
-        Ok(Some(LocalMetadata {
-            title: movie.title,
-            overview: movie.overview,
-            poster_path: movie.poster_path,
-            tmdb_id: movie.id,
-            episode_number: None,
-        }))
-    } else {
-        Ok(None)
+    // The localized search came back with no overview -- fall back to
+    // English and fill in only what's still missing, rather than losing the
+    // description entirely.
+    if movie.overview.is_empty() && config.metadata_language != ENGLISH_FALLBACK_LANGUAGE {
+        match search_tmdb_results(config, endpoint, query, year, ENGLISH_FALLBACK_LANGUAGE).await {
+            Ok(fallback_results) => {
+                if let Some(fallback) = fallback_results.into_iter().find(|r| r.id == movie.id) {
+                    if movie.overview.is_empty() {
+                        movie.overview = fallback.overview;
+                    }
+                    if movie.poster_path.is_none() {
+                        movie.poster_path = fallback.poster_path;
+                    }
+                    if movie.original_title.is_none() {
+                        movie.original_title = fallback.original_title;
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("[metadata] English fallback search failed: {}", e);
+            }
+        }
     }
+
+    Ok(Some(LocalMetadata {
+        title: movie.title,
+        overview: movie.overview,
+        poster_path: movie.poster_path,
+        tmdb_id: movie.id,
+        episode_number: None,
+        technical: None,
+        blurhash: None,
+        original_title: movie.original_title,
+        classification_signal: None,
+    }))
 }
 
-pub async fn fetch_tmdb_season_metadata(
+async fn fetch_tmdb_season(
     config: &crate::models::AppConfig,
     tmdb_id: u64,
     season_number: u32,
-) -> Result<Option<LocalMetadata>> {
-    let client = reqwest::Client::new();
+    language: &str,
+) -> Result<(u16, Vec<u8>)> {
     let url = format!(
-        "{}/tv/{}/season/{}?language=en-US",
-        config.tmdb_base_url, tmdb_id, season_number
+        "{}/tv/{}/season/{}?language={}",
+        config.tmdb_base_url, tmdb_id, season_number, language
     );
+    println!("[metadata] Request URL: {}", url);
+    TmdbClient::global()
+        .get(url, tmdb_token(config))
+        .await
+        .context("Failed to send TMDB season request")
+}
 
+pub async fn fetch_tmdb_season_metadata(
+    config: &crate::models::AppConfig,
+    tmdb_id: u64,
+    season_number: u32,
+) -> Result<Option<LocalMetadata>> {
     println!(
         "[metadata] Fetching TMDB Season: Show={}, Season={}",
         tmdb_id, season_number
     );
-    println!("[metadata] Request URL: {}", url);
-
-    let mut req = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
 
-    let token = if !config.tmdb_access_token.is_empty() {
-        &config.tmdb_access_token
-    } else {
-        crate::models::DEFAULT_TMDB_ACCESS_TOKEN
-    };
+    let (status, body) =
+        fetch_tmdb_season(config, tmdb_id, season_number, &config.metadata_language).await?;
+    println!("[metadata] Response status: {}", status);
 
-    if !token.is_empty() {
-        req = req.header("Authorization", format!("Bearer {}", token));
+    if status == 404 {
+        return Ok(None);
     }
-
-    let resp = req
-        .send()
-        .await
-        .context("Failed to send TMDB season request")?;
-
-    println!("[metadata] Response status: {}", resp.status());
-
-    if !resp.status().is_success() {
-        if resp.status() == 404 {
-            return Ok(None);
-        }
-        let error_text = resp.text().await.unwrap_or_else(|_| "Unknown error".into());
+    if status < 200 || status >= 300 {
+        let error_text = String::from_utf8_lossy(&body);
         println!("[metadata] API Error Body: {}", error_text);
         return Err(anyhow::anyhow!("TMDB API Error"));
     }
 
-    let season_res: TmdbSeasonResponse =
-        resp.json().await.context("Failed to parse TMDB response")?;
+    let mut season_res: TmdbSeasonResponse =
+        serde_json::from_slice(&body).context("Failed to parse TMDB response")?;
+
+    if season_res.overview.is_empty() && config.metadata_language != ENGLISH_FALLBACK_LANGUAGE {
+        if let Ok((200..=299, body)) =
+            fetch_tmdb_season(config, tmdb_id, season_number, ENGLISH_FALLBACK_LANGUAGE).await
+            && let Ok(fallback) = serde_json::from_slice::<TmdbSeasonResponse>(&body)
+        {
+            if season_res.overview.is_empty() {
+                season_res.overview = fallback.overview;
+            }
+            if season_res.poster_path.is_none() {
+                season_res.poster_path = fallback.poster_path;
+            }
+        }
+    }
 
     Ok(Some(LocalMetadata {
         title: season_res.name,
@@ -256,61 +1217,137 @@ pub async fn fetch_tmdb_season_metadata(
         poster_path: season_res.poster_path,
         tmdb_id: season_res.id,
         episode_number: None,
+        technical: None,
+        blurhash: None,
+        original_title: None,
+        classification_signal: None,
     }))
 }
 
-pub async fn fetch_tmdb_episode_metadata(
+/// Resolves an anime's absolute episode number (no season marker, e.g. the
+/// `- 012` a fansub release uses) to a `(season, episode)` pair, by walking
+/// the show's seasons in TMDB order and summing episode counts until the
+/// running total reaches `absolute_episode`. Stops at the first season TMDB
+/// 404s on, or a hard cap, whichever comes first -- real shows don't have
+/// more than a handful of dozens of seasons, and a 404 is TMDB's own signal
+/// that the season list ended.
+async fn resolve_absolute_episode(
+    config: &crate::models::AppConfig,
+    tmdb_id: u64,
+    absolute_episode: u32,
+) -> Result<Option<(u32, u32)>> {
+    const MAX_SEASONS: u32 = 50;
+    let mut running_total: u32 = 0;
+
+    for season_number in 1..=MAX_SEASONS {
+        let (status, body) =
+            fetch_tmdb_season(config, tmdb_id, season_number, &config.metadata_language).await?;
+        if status == 404 {
+            break;
+        }
+        if !(200..300).contains(&status) {
+            break;
+        }
+
+        let season_res: TmdbSeasonResponse =
+            serde_json::from_slice(&body).context("Failed to parse TMDB response")?;
+        let episode_count = season_res.episodes.len() as u32;
+        if episode_count == 0 {
+            break;
+        }
+
+        if absolute_episode <= running_total + episode_count {
+            return Ok(Some((season_number, absolute_episode - running_total)));
+        }
+        running_total += episode_count;
+    }
+
+    tracing::debug!(
+        "[metadata] Could not resolve absolute episode {} for show {} (seasons summed to {})",
+        absolute_episode,
+        tmdb_id,
+        running_total
+    );
+    Ok(None)
+}
+
+async fn fetch_tmdb_episode(
     config: &crate::models::AppConfig,
     tmdb_id: u64,
     season_number: u32,
     episode_number: u32,
-) -> Result<Option<LocalMetadata>> {
-    let client = reqwest::Client::new();
+    language: &str,
+) -> Result<(u16, Vec<u8>)> {
     let url = format!(
-        "{}/tv/{}/season/{}/episode/{}?language=en-US",
-        config.tmdb_base_url, tmdb_id, season_number, episode_number
+        "{}/tv/{}/season/{}/episode/{}?language={}",
+        config.tmdb_base_url, tmdb_id, season_number, episode_number, language
     );
+    TmdbClient::global()
+        .get(url, tmdb_token(config))
+        .await
+        .context("Failed to send TMDB episode request")
+}
 
+pub async fn fetch_tmdb_episode_metadata(
+    config: &crate::models::AppConfig,
+    tmdb_id: u64,
+    season_number: u32,
+    episode_number: u32,
+) -> Result<Option<LocalMetadata>> {
     println!(
         "[metadata] Fetching TMDB Episode: Show={}, S{:02}E{:02}",
         tmdb_id, season_number, episode_number
     );
 
-    let mut req = client
-        .get(&url)
-        .header("Accept", "application/json")
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36");
-
-    let token = if !config.tmdb_access_token.is_empty() {
-        &config.tmdb_access_token
-    } else {
-        crate::models::DEFAULT_TMDB_ACCESS_TOKEN
-    };
-
-    if !token.is_empty() {
-        req = req.header("Authorization", format!("Bearer {}", token));
+    let (status, body) = fetch_tmdb_episode(
+        config,
+        tmdb_id,
+        season_number,
+        episode_number,
+        &config.metadata_language,
+    )
+    .await?;
+
+    if status == 404 {
+        return Ok(None);
+    }
+    if status < 200 || status >= 300 {
+        return Err(anyhow::anyhow!("TMDB API Error: {}", status));
     }
 
-    let resp = req
-        .send()
+    let mut ep_res: TmdbEpisodeResponse =
+        serde_json::from_slice(&body).context("Failed to parse TMDB response")?;
+
+    if ep_res.overview.is_empty() && config.metadata_language != ENGLISH_FALLBACK_LANGUAGE {
+        if let Ok((200..=299, body)) = fetch_tmdb_episode(
+            config,
+            tmdb_id,
+            season_number,
+            episode_number,
+            ENGLISH_FALLBACK_LANGUAGE,
+        )
         .await
-        .context("Failed to send TMDB episode request")?;
-
-    if !resp.status().is_success() {
-        if resp.status() == 404 {
-            return Ok(None);
+            && let Ok(fallback) = serde_json::from_slice::<TmdbEpisodeResponse>(&body)
+        {
+            if ep_res.overview.is_empty() {
+                ep_res.overview = fallback.overview;
+            }
+            if ep_res.still_path.is_none() {
+                ep_res.still_path = fallback.still_path;
+            }
         }
-        return Err(anyhow::anyhow!("TMDB API Error: {}", resp.status()));
     }
 
-    let ep_res: TmdbEpisodeResponse = resp.json().await.context("Failed to parse TMDB response")?;
-
     Ok(Some(LocalMetadata {
         title: ep_res.name,
         overview: ep_res.overview,
         poster_path: ep_res.still_path, // Use still_path for episodes
         tmdb_id: ep_res.id,
         episode_number: Some(ep_res.episode_number),
+        technical: None,
+        blurhash: None,
+        original_title: None,
+        classification_signal: None,
     }))
 }
 
@@ -322,19 +1359,16 @@ pub async fn download_image(
     let url = format!("{}{}", config.tmdb_image_base_url, poster_suffix);
     println!("[metadata] Downloading image from: {}", url);
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(120))
-        .build()
-        .context("Failed to build HTTP client")?;
-
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-        .send()
+    let (status, bytes) = TmdbClient::global()
+        .get(url, None)
         .await
         .context("Failed to download image")?;
-
-    let bytes = resp.bytes().await.context("Failed to get image bytes")?;
+    if status < 200 || status >= 300 {
+        return Err(anyhow::anyhow!(
+            "Image download failed with status {}",
+            status
+        ));
+    }
     println!("[metadata] Downloaded {} bytes", bytes.len());
 
     let mut file = fs::File::create(target_path)
@@ -347,41 +1381,179 @@ pub async fn download_image(
     Ok(())
 }
 
+/// Downloads `poster_suffix` to `target_path` and computes a BlurHash
+/// placeholder for it. Returns `None` (logging the cause) if either step
+/// fails -- a missing BlurHash shouldn't fail the metadata save that
+/// triggered it.
+pub async fn download_poster_with_blurhash(
+    config: &crate::models::AppConfig,
+    poster_suffix: &str,
+    target_path: &Path,
+) -> Option<String> {
+    if let Err(e) = download_image(config, poster_suffix, target_path).await {
+        eprintln!("[metadata] Failed to download image: {}", e);
+        return None;
+    }
+
+    match crate::blurhash::encode_image_file(
+        target_path,
+        crate::blurhash::DEFAULT_SAMPLE_WIDTH,
+        crate::blurhash::DEFAULT_SAMPLE_HEIGHT,
+        crate::blurhash::DEFAULT_COMPONENTS_X,
+        crate::blurhash::DEFAULT_COMPONENTS_Y,
+    )
+    .await
+    {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            eprintln!(
+                "[metadata] Failed to compute BlurHash for {:?}: {}",
+                target_path, e
+            );
+            None
+        }
+    }
+}
+
 pub async fn process_file(
     path: &Path,
     config: &crate::models::AppConfig,
-    is_tv: bool,
+    is_tv_library: bool,
+    is_anime_library: bool,
 ) -> Result<Option<LocalMetadata>> {
     let file_name = path
         .file_name()
         .context("No filename")?
         .to_string_lossy()
         .to_string();
-    let (cleaned_name, year) = cleanup_filename(&file_name);
 
-    println!(
-        "[process_file] Processing: {} (cleaned: '{}', year: {:?}, is_tv: {})",
-        file_name, cleaned_name, year, is_tv
-    );
+    // `library.kind` alone gets mixed libraries and mislabelled files
+    // wrong (a show dropped in a Movies library, or vice versa) -- so an
+    // episode/date token detected in the filename itself always promotes
+    // the lookup to TV, regardless of which library it was scanned from.
+    let classification = classify_media_kind(&file_name);
+    let is_tv = is_tv_library || classification.is_tv;
+    if is_tv != is_tv_library {
+        tracing::debug!(
+            "[process_file] {} reclassified as TV ({:?}, detected series: '{}')",
+            file_name,
+            classification.signal,
+            classification.series_name
+        );
+    }
 
-    // 1. Fetch Metadata
-    // For now, only basic fetch (no season logic here yet, scanner skips season logic for now or we add it later)
-    // Actually, let's keep it simple: if is_tv is true, we treat it as a show search.
-    // NOTE: This basic processor doesn't handle the sophisticated season detection from video.rs yet.
-    // For "Movies" library, is_tv will be false.
+    // For TV libraries, resolve the show first, then chain into the
+    // episode (or, failing that, season) lookup using the parsed numbers.
+    // Movie libraries don't carry season/episode markers, so they keep
+    // using the simpler `cleanup_filename` + single search.
+    let best_match = if is_tv && is_anime_library {
+        let (series_title, absolute_episode) = parse_anime_filename(&file_name);
+        println!(
+            "[process_file] Parsed anime filename: {} -> title='{}', absolute_episode={:?}",
+            file_name, series_title, absolute_episode
+        );
+
+        let show = fetch_tmdb_metadata(config, &series_title, None, true)
+            .await
+            .ok()
+            .flatten();
+
+        match (show, absolute_episode) {
+            (Some(show), Some(absolute)) => {
+                match resolve_absolute_episode(config, show.tmdb_id, absolute).await {
+                    Ok(Some((season, episode))) => {
+                        fetch_tmdb_episode_metadata(config, show.tmdb_id, season, episode)
+                            .await
+                            .ok()
+                            .flatten()
+                    }
+                    Ok(None) => {
+                        println!(
+                            "[process_file] Could not resolve absolute episode {} for '{}'",
+                            absolute, series_title
+                        );
+                        Some(show)
+                    }
+                    Err(e) => {
+                        eprintln!("[process_file] Absolute episode resolution failed: {}", e);
+                        Some(show)
+                    }
+                }
+            }
+            (Some(show), None) => Some(show),
+            (None, _) => None,
+        }
+    } else if is_tv {
+        let parsed = parse_filename(&file_name);
+        println!(
+            "[process_file] Parsed TV filename: {} -> {:?}",
+            file_name, parsed
+        );
+
+        let show = fetch_tmdb_metadata(config, &parsed.title, parsed.year.as_deref(), true)
+            .await
+            .ok()
+            .flatten();
+
+        match (show, parsed.season, parsed.episode, parsed.absolute_episode) {
+            (Some(show), Some(season), Some(episode), _) => {
+                fetch_tmdb_episode_metadata(config, show.tmdb_id, season, episode)
+                    .await
+                    .ok()
+                    .flatten()
+            }
+            (Some(show), Some(season), None, _) => {
+                fetch_tmdb_season_metadata(config, show.tmdb_id, season)
+                    .await
+                    .ok()
+                    .flatten()
+            }
+            // No `SxxExx` matched, but a bare absolute number did -- anime
+            // releases slip into non-anime-flagged TV libraries often
+            // enough that this is worth resolving too, not just erroring.
+            (Some(show), None, None, Some(absolute)) => {
+                match resolve_absolute_episode(config, show.tmdb_id, absolute).await {
+                    Ok(Some((season, episode))) => {
+                        fetch_tmdb_episode_metadata(config, show.tmdb_id, season, episode)
+                            .await
+                            .ok()
+                            .flatten()
+                    }
+                    _ => Some(show),
+                }
+            }
+            (Some(show), None, _, _) => Some(show),
+            (None, _, _, _) => None,
+        }
+    } else {
+        let (cleaned_name, year, release_info) = cleanup_filename(&file_name);
+
+        println!(
+            "[process_file] Processing: {} (cleaned: '{}', year: {:?}, is_tv: {})",
+            file_name, cleaned_name, year, is_tv
+        );
+        tracing::debug!(
+            "[process_file] Release info for {}: {:?} (quality_score={})",
+            file_name,
+            release_info,
+            release_info.quality_score()
+        );
+
+        fetch_tmdb_metadata(config, &cleaned_name, year.as_deref(), is_tv)
+            .await
+            .ok()
+            .flatten()
+    };
 
-    let best_match = fetch_tmdb_metadata(config, &cleaned_name, year.as_deref(), is_tv)
-        .await
-        .ok()
-        .flatten();
+    if let Some(mut m) = best_match {
+        if is_tv != is_tv_library {
+            m.classification_signal = Some(classification.signal);
+        }
 
-    if let Some(m) = best_match {
-        // 2. Download Poster
-        if let Some(poster_suffix) = &m.poster_path {
+        // 2. Download Poster (+ BlurHash placeholder)
+        if let Some(poster_suffix) = m.poster_path.clone() {
             let img_path = path.parent().unwrap().join(format!("{}.jpg", file_name));
-            if let Err(e) = download_image(config, poster_suffix, &img_path).await {
-                eprintln!("[process_file] Failed to download image: {}", e);
-            }
+            m.blurhash = download_poster_with_blurhash(config, &poster_suffix, &img_path).await;
         }
 
         // 3. Save JSON
@@ -430,7 +1602,7 @@ mod tests {
         ];
 
         for (input, expected_title, expected_year) in cases {
-            let (title, year) = cleanup_filename(input);
+            let (title, year, _release_info) = cleanup_filename(input);
             assert_eq!(
                 title, expected_title,
                 "Failed on title for input: {}",
@@ -444,4 +1616,59 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_release_info() {
+        let info = parse_release_info("Movie.Title.2023.2160p.BluRay.x265.DTS-GROUP.mkv");
+        assert_eq!(info.resolution, Some(ReleaseResolution::Uhd));
+        assert_eq!(info.source, Some(ReleaseSource::BluRay));
+        assert_eq!(info.video_codec, Some(VideoCodec::H265));
+        assert_eq!(info.audio_codec, Some(AudioCodec::Dts));
+        assert!(!info.is_cam);
+
+        let cam = parse_release_info("Movie.Title.2023.HDCAM.x264-GROUP.mkv");
+        assert!(cam.is_cam);
+        assert!(cam.quality_score() < 0);
+
+        let clean = parse_release_info("Movie.Title.2023.1080p.WEB-DL.x264.AAC-GROUP.mkv");
+        assert_eq!(clean.resolution, Some(ReleaseResolution::Fhd));
+        assert_eq!(clean.source, Some(ReleaseSource::WebDl));
+        assert!(clean.quality_score() > cam.quality_score());
+    }
+
+    #[test]
+    fn test_classify_media_kind() {
+        let episode = classify_media_kind("Breaking.Bad.S01E02.1080p.mkv");
+        assert!(episode.is_tv);
+        assert_eq!(episode.signal, ClassificationSignal::EpisodeNumber);
+
+        let daily = classify_media_kind("The.Daily.Show.2024.03.15.mkv");
+        assert!(daily.is_tv);
+        assert_eq!(daily.signal, ClassificationSignal::AirDate);
+        assert_eq!(daily.series_name, "The Daily Show");
+
+        let movie = classify_media_kind("Another Movie (1999) [Bluray].mkv");
+        assert!(!movie.is_tv);
+        assert_eq!(movie.signal, ClassificationSignal::Year);
+
+        let unknown = classify_media_kind("random_home_video.mp4");
+        assert!(!unknown.is_tv);
+        assert_eq!(unknown.signal, ClassificationSignal::None);
+    }
+
+    #[test]
+    fn test_parse_anime_filename() {
+        let (title, episode) =
+            parse_anime_filename("[SubGroup] Show Name - 012 (1080p) [ABCD1234].mkv");
+        assert_eq!(title, "Show Name");
+        assert_eq!(episode, Some(12));
+
+        let (title, episode) = parse_anime_filename("[Other-Fansub] Another Show - 1.mkv");
+        assert_eq!(title, "Another Show");
+        assert_eq!(episode, Some(1));
+
+        let (title, episode) = parse_anime_filename("No Tags Show Name.mkv");
+        assert_eq!(title, "No Tags Show Name");
+        assert_eq!(episode, None);
+    }
 }
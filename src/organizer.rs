@@ -0,0 +1,359 @@
+//! Library organizer: once a file has matched TMDB metadata (via
+//! `metadata::process_file` or the scanner's season/episode tasks), moves,
+//! copies, hardlinks, or symlinks it into a canonical Plex/Kodi-style
+//! library layout instead of leaving it wherever it was scanned from, per a
+//! per-library format template (`DEFAULT_MOVIE_TEMPLATE`/
+//! `DEFAULT_EPISODE_TEMPLATE` unless `Library::movie_format_template`/
+//! `episode_format_template` override them) -- see `render_template` for the
+//! `{token}`/`{token:02}`/`{cond ? "a" : "b"}` placeholder syntax.
+//!
+//! `AppConfig::organize_enabled`/`organize_action`/`organize_conflict` are
+//! reserved for a future automatic post-scan organize pass; the manual
+//! one-shot `routes::library::organize_path` endpoint calls `organize_file`
+//! directly and isn't gated on them. Callers pass an `OrganizeTarget`
+//! describing what the matched file is (a movie, or a specific episode),
+//! the configured organized root (`AppConfig::organized_library_dir`,
+//! shared by every library), and the file's matched path;
+//! `organize_file` returns the planned (or, when not a dry run, completed)
+//! `OrganizePlan`, or `None` when the conflict policy decided to skip it.
+
+use anyhow::{Context, Result, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// How a matched file is relocated into the organized tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizeAction {
+    Copy,
+    Move,
+    Hardlink,
+    Symlink,
+}
+
+/// What to do when the destination path is already occupied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Leave the source where it is.
+    Skip,
+    /// Replace whatever is at the destination.
+    Overwrite,
+    /// Append " (2)", " (3)", ... to the file stem until a free name is found.
+    Index,
+    /// Return an error instead of relocating anything.
+    Fail,
+}
+
+/// What a matched file is, for the purposes of building its destination
+/// path. Built by the caller from whatever `metadata::process_file` (plus,
+/// for episodes, the parsed season/episode numbers) already resolved.
+#[derive(Debug, Clone)]
+pub enum OrganizeTarget {
+    Movie {
+        title: String,
+        year: Option<String>,
+    },
+    Episode {
+        show_title: String,
+        season: u32,
+        episode: u32,
+        episode_title: String,
+        /// Season 0 ("Specials") -- lets a format template route these to a
+        /// different folder via `{special ? "Specials" : "Season {season:02}"}`.
+        special: bool,
+    },
+}
+
+/// Default per-library format templates, matching the hardcoded layout this
+/// module used before templates existed. A library without its own
+/// `movie_format_template`/`episode_format_template` gets these.
+pub const DEFAULT_MOVIE_TEMPLATE: &str = "{title} ({year})/{title} ({year})";
+pub const DEFAULT_EPISODE_TEMPLATE: &str = "{series}/{special ? \"Specials\" : \"Season {season:02}\"}/{series} - S{season:02}E{episode:02} - {episode_title}";
+
+/// Substitution values for a format template. Strings and numbers are
+/// plugged into `{token}`/`{token:02}` placeholders; bools are only usable
+/// as the condition of a `{token ? "a" : "b"}` ternary.
+#[derive(Default)]
+struct TemplateContext {
+    strings: HashMap<&'static str, String>,
+    numbers: HashMap<&'static str, u32>,
+    bools: HashMap<&'static str, bool>,
+}
+
+impl TemplateContext {
+    fn for_movie(title: &str, year: Option<&str>) -> Self {
+        let mut ctx = Self::default();
+        ctx.strings.insert("title", title.to_string());
+        ctx.strings
+            .insert("year", year.unwrap_or_default().to_string());
+        ctx
+    }
+
+    fn for_episode(
+        show_title: &str,
+        season: u32,
+        episode: u32,
+        episode_title: &str,
+        special: bool,
+    ) -> Self {
+        let mut ctx = Self::default();
+        ctx.strings.insert("series", show_title.to_string());
+        ctx.strings
+            .insert("episode_title", episode_title.to_string());
+        ctx.numbers.insert("season", season);
+        ctx.numbers.insert("episode", episode);
+        ctx.bools.insert("special", special);
+        ctx
+    }
+}
+
+/// Renders `template` against `ctx`: evaluates `{cond ? "a" : "b"}` ternaries
+/// first (recursing into the winning branch, since it may itself contain
+/// placeholders), then substitutes remaining `{token}`/`{token:02}`
+/// placeholders. Unknown tokens render as an empty string.
+fn render_template(template: &str, ctx: &TemplateContext) -> String {
+    render_placeholders(&render_ternaries(template, ctx), ctx)
+}
+
+fn render_ternaries(template: &str, ctx: &TemplateContext) -> String {
+    static TERNARY_RE: OnceLock<Regex> = OnceLock::new();
+    let re = TERNARY_RE
+        .get_or_init(|| Regex::new(r#"\{(\w+)\s*\?\s*"([^"]*)"\s*:\s*"([^"]*)"\}"#).unwrap());
+    re.replace_all(template, |caps: &regex::Captures| {
+        let cond = ctx.bools.get(&caps[1]).copied().unwrap_or(false);
+        let branch = if cond { &caps[2] } else { &caps[3] };
+        render_placeholders(branch, ctx)
+    })
+    .to_string()
+}
+
+fn render_placeholders(template: &str, ctx: &TemplateContext) -> String {
+    static PLACEHOLDER_RE: OnceLock<Regex> = OnceLock::new();
+    let re = PLACEHOLDER_RE.get_or_init(|| Regex::new(r"\{(\w+)(?::(\d+))\}|\{(\w+)\}").unwrap());
+    re.replace_all(template, |caps: &regex::Captures| {
+        let (key, width) = match (caps.get(1), caps.get(3)) {
+            (Some(key), _) => (
+                key.as_str(),
+                caps.get(2).and_then(|w| w.as_str().parse().ok()),
+            ),
+            (_, Some(key)) => (key.as_str(), None),
+            _ => return String::new(),
+        };
+        if let Some(n) = ctx.numbers.get(key) {
+            match width {
+                Some(width) => format!("{:0width$}", n, width = width),
+                None => n.to_string(),
+            }
+        } else {
+            ctx.strings.get(key).cloned().unwrap_or_default()
+        }
+    })
+    .to_string()
+}
+
+/// Strips characters that are illegal (or just awkward) in a path
+/// component on common filesystems, and trims the trailing dots/spaces
+/// Windows rejects.
+fn sanitize_path_component(component: &str) -> String {
+    static ILLEGAL_RE: OnceLock<Regex> = OnceLock::new();
+    let illegal_re = ILLEGAL_RE.get_or_init(|| Regex::new(r#"[<>:"/\\|?*\x00-\x1F]"#).unwrap());
+    illegal_re
+        .replace_all(component, "")
+        .trim()
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Builds the destination path for `target` under `library_root` by
+/// rendering `template` (a `/`-separated path with `{token}` placeholders,
+/// see `render_template`), sanitizing each resulting component, and
+/// appending the source file's extension to the last one.
+fn destination_path(
+    library_root: &Path,
+    target: &OrganizeTarget,
+    ext: &str,
+    template: &str,
+) -> PathBuf {
+    let ctx = match target {
+        OrganizeTarget::Movie { title, year } => TemplateContext::for_movie(title, year.as_deref()),
+        OrganizeTarget::Episode {
+            show_title,
+            season,
+            episode,
+            episode_title,
+            special,
+        } => TemplateContext::for_episode(show_title, *season, *episode, episode_title, *special),
+    };
+
+    let rendered = render_template(template, &ctx);
+    let components: Vec<&str> = rendered.split('/').filter(|c| !c.is_empty()).collect();
+    let last = components.len().saturating_sub(1);
+
+    let mut path = library_root.to_path_buf();
+    for (i, component) in components.iter().enumerate() {
+        let sanitized = sanitize_path_component(component);
+        if i == last {
+            path.push(format!("{}.{}", sanitized, ext));
+        } else {
+            path.push(sanitized);
+        }
+    }
+    path
+}
+
+/// Applies `policy` to `dest`: `Ok(None)` means "don't relocate anything",
+/// `Ok(Some(path))` is the path to actually relocate to.
+async fn resolve_conflict(dest: PathBuf, policy: ConflictPolicy) -> Result<Option<PathBuf>> {
+    if !tokio::fs::try_exists(&dest).await.unwrap_or(false) {
+        return Ok(Some(dest));
+    }
+
+    match policy {
+        ConflictPolicy::Skip => Ok(None),
+        ConflictPolicy::Overwrite => Ok(Some(dest)),
+        ConflictPolicy::Fail => Err(anyhow!("Destination {:?} already exists", dest)),
+        ConflictPolicy::Index => {
+            let parent = dest.parent().unwrap_or_else(|| Path::new("."));
+            let stem = dest
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "file".to_string());
+            let ext = dest.extension().map(|e| e.to_string_lossy().to_string());
+
+            for index in 2..1000 {
+                let candidate_name = match &ext {
+                    Some(ext) => format!("{} ({}).{}", stem, index, ext),
+                    None => format!("{} ({})", stem, index),
+                };
+                let candidate = parent.join(candidate_name);
+                if !tokio::fs::try_exists(&candidate).await.unwrap_or(false) {
+                    return Ok(Some(candidate));
+                }
+            }
+            Ok(None)
+        }
+    }
+}
+
+/// The sidecar path the rest of the codebase uses for a media file:
+/// `metadata::save_local_metadata` and `metadata::process_file`'s poster
+/// download both key sidecars off `{file_name}{suffix}`.
+fn sidecar_path(path: &Path, suffix: &str) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}{}", file_name, suffix))
+}
+
+/// Performs the actual filesystem action. `Move` falls back to copy+delete
+/// when `rename` fails (e.g. `EXDEV`, crossing a filesystem boundary).
+async fn relocate(source: &Path, dest: &Path, action: OrganizeAction) -> Result<()> {
+    match action {
+        OrganizeAction::Copy => {
+            tokio::fs::copy(source, dest)
+                .await
+                .with_context(|| format!("Failed to copy {:?} to {:?}", source, dest))?;
+        }
+        OrganizeAction::Move => {
+            if tokio::fs::rename(source, dest).await.is_err() {
+                tokio::fs::copy(source, dest)
+                    .await
+                    .with_context(|| format!("Failed to copy {:?} to {:?}", source, dest))?;
+                tokio::fs::remove_file(source)
+                    .await
+                    .with_context(|| format!("Failed to remove {:?} after copy", source))?;
+            }
+        }
+        OrganizeAction::Hardlink => {
+            tokio::fs::hard_link(source, dest)
+                .await
+                .with_context(|| format!("Failed to hardlink {:?} to {:?}", source, dest))?;
+        }
+        OrganizeAction::Symlink => {
+            #[cfg(unix)]
+            {
+                tokio::fs::symlink(source, dest)
+                    .await
+                    .with_context(|| format!("Failed to symlink {:?} to {:?}", source, dest))?;
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(anyhow::anyhow!(
+                    "Symlink organize action isn't supported on this platform"
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A single planned (or, when `dry_run` was false, completed) relocation.
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizePlan {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Computes (and, unless `dry_run`, performs) the relocation of `source`
+/// (and its `.json`/`.jpg` sidecars, if present) into the layout described
+/// by `template` (see `render_template`), under `library_root`, per `action`/
+/// `conflict`.
+///
+/// Returns `None` when `conflict` is `ConflictPolicy::Skip` and the
+/// destination already exists; `ConflictPolicy::Fail` returns `Err` instead.
+pub async fn organize_file(
+    source: &Path,
+    target: &OrganizeTarget,
+    library_root: &Path,
+    template: &str,
+    action: OrganizeAction,
+    conflict: ConflictPolicy,
+    dry_run: bool,
+) -> Result<Option<OrganizePlan>> {
+    let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+    let dest = destination_path(library_root, target, ext, template);
+
+    let Some(dest) = resolve_conflict(dest, conflict).await? else {
+        println!(
+            "[organizer] Skipping {:?}: destination already exists and conflict policy is Skip",
+            source
+        );
+        return Ok(None);
+    };
+
+    if dry_run {
+        return Ok(Some(OrganizePlan {
+            from: source.to_path_buf(),
+            to: dest,
+        }));
+    }
+
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory {:?}", parent))?;
+    }
+
+    relocate(source, &dest, action).await?;
+    println!("[organizer] {:?}'d {:?} -> {:?}", action, source, dest);
+
+    for suffix in [".json", ".jpg"] {
+        let sidecar_src = sidecar_path(source, suffix);
+        if tokio::fs::try_exists(&sidecar_src).await.unwrap_or(false) {
+            let sidecar_dest = sidecar_path(&dest, suffix);
+            if let Err(e) = relocate(&sidecar_src, &sidecar_dest, action).await {
+                eprintln!(
+                    "[organizer] Failed to relocate sidecar {:?}: {}",
+                    sidecar_src, e
+                );
+            }
+        }
+    }
+
+    Ok(Some(OrganizePlan {
+        from: source.to_path_buf(),
+        to: dest,
+    }))
+}
@@ -0,0 +1,92 @@
+//! Resolver for `LibraryType::Remote` libraries: shells out to `yt-dlp`
+//! (must be on `PATH`) to list a playlist/channel's entries and, separately,
+//! to resolve one entry down to a direct, streamable media URL.
+//!
+//! `routes::ui::get_files_for_ui` calls `list_entries` to populate
+//! `FileView`s for a remote library the same way it reads a directory for a
+//! local one; `routes::library::serve_content` calls `resolve_direct_url`
+//! and redirects to it instead of reading from a `store::MediaStore`, since
+//! there's nothing on local disk to stream.
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use tokio::process::Command;
+
+/// One playlist/channel entry, as surfaced to `routes::ui::FileView`.
+#[derive(Debug, Clone)]
+pub struct RemoteEntry {
+    pub id: String,
+    pub title: String,
+    pub duration_secs: Option<f64>,
+    pub thumbnail_url: Option<String>,
+}
+
+/// Raw shape of the single JSON object `yt-dlp -J --flat-playlist` prints:
+/// a playlist/channel with its items under `entries`, or a lone video with
+/// no `entries`.
+#[derive(Debug, Deserialize)]
+struct YtDlpEntry {
+    id: String,
+    title: Option<String>,
+    duration: Option<f64>,
+    thumbnail: Option<String>,
+    entries: Option<Vec<YtDlpEntry>>,
+}
+
+/// Lists the entries of a playlist/channel (or the single entry of a lone
+/// video URL) without resolving playable formats -- `--flat-playlist` keeps
+/// this fast enough to call on every `get_files_for_ui` request.
+pub async fn list_entries(url: &str) -> Result<Vec<RemoteEntry>> {
+    let output = Command::new("yt-dlp")
+        .args(["-J", "--flat-playlist", "--no-warnings", url])
+        .output()
+        .await
+        .context("Failed to run yt-dlp")?;
+
+    if !output.status.success() {
+        bail!(
+            "yt-dlp exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let root: YtDlpEntry =
+        serde_json::from_slice(&output.stdout).context("Failed to parse yt-dlp JSON output")?;
+
+    let raw_entries = root.entries.unwrap_or_else(|| vec![root]);
+    Ok(raw_entries
+        .into_iter()
+        .map(|e| RemoteEntry {
+            title: e.title.clone().unwrap_or_else(|| e.id.clone()),
+            id: e.id,
+            duration_secs: e.duration,
+            thumbnail_url: e.thumbnail,
+        })
+        .collect())
+}
+
+/// Resolves a single video id/URL down to a direct, streamable media URL
+/// (`yt-dlp -f best -g`), for `routes::library::serve_content` to redirect
+/// to rather than reading from disk.
+pub async fn resolve_direct_url(video_id_or_url: &str) -> Result<String> {
+    let output = Command::new("yt-dlp")
+        .args(["-f", "best", "-g", "--no-warnings", video_id_or_url])
+        .output()
+        .await
+        .context("Failed to run yt-dlp")?;
+
+    if !output.status.success() {
+        bail!(
+            "yt-dlp exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if url.is_empty() {
+        bail!("yt-dlp returned no direct URL for {}", video_id_or_url);
+    }
+    Ok(url)
+}
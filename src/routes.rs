@@ -0,0 +1,9 @@
+//! HTTP handlers for the auth/library-backed app (`models::AppState`),
+//! grouped by concern. None of these were ever declared as part of the
+//! crate before this module file existed, so nothing here was reachable
+//! from `main.rs`.
+
+pub mod feed;
+pub mod library;
+pub mod ui;
+pub mod video;
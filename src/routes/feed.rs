@@ -0,0 +1,170 @@
+//! Per-library RSS 2.0 (+ iTunes podcast extensions) feed, so an external
+//! podcast/video client can subscribe to a library and handle
+//! downloads/enclosures/progress itself instead of going through this
+//! app's own UI. Reuses `routes::ui::get_files_for_ui` for traversal --
+//! recursing into subdirectories, since a client subscribing to a library
+//! wants one flat feed of episodes/movies, not a folder tree -- and
+//! `metadata::read_local_metadata` for titles/posters/duration.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+use crate::models::AppState;
+use crate::routes::ui::{FileView, get_files_for_ui};
+use crate::routes::video::get_base_path;
+
+#[derive(Deserialize)]
+pub struct FeedParams {
+    pub token: String,
+}
+
+/// Mints the signed `?token=` this feed's URL requires (see `feed_handler`),
+/// handed out once so it can be pasted into a podcast/video client.
+///
+/// Intended to be layered behind `auth::require_admin`, like the rest of
+/// the library-management routes.
+pub async fn feed_token_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let exists = state.libraries.read().await.iter().any(|l| l.id == id);
+    if !exists {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    crate::auth::mint_feed_token(&id, &state.auth.config).into_response()
+}
+
+/// Serves `GET /api/libraries/:id/feed.xml`: an RSS 2.0 feed of every
+/// playable file under library `id`, gated by `params.token` (see
+/// `feed_token_handler`/`auth::verify_feed_token`) since podcast/video
+/// clients fetching the feed and its enclosures can't send the `session`
+/// cookie.
+pub async fn feed_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<FeedParams>,
+) -> impl IntoResponse {
+    let lib = {
+        let libraries = state.libraries.read().await;
+        match libraries.iter().find(|l| l.id == id) {
+            Some(lib) => lib.clone(),
+            None => return (StatusCode::NOT_FOUND, "Library not found").into_response(),
+        }
+    };
+
+    if !crate::auth::verify_feed_token(&params.token, &id, &state.auth.config) {
+        return (StatusCode::FORBIDDEN, "Invalid or expired feed token").into_response();
+    }
+
+    let mut items = Vec::new();
+    collect_items(&state, &id, String::new(), &mut items).await;
+
+    let channel_title = escape_xml(&lib.name);
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(
+        "<rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\">\n<channel>\n",
+    );
+    xml.push_str(&format!("<title>{}</title>\n", channel_title));
+    xml.push_str(&format!(
+        "<itunes:author>{}</itunes:author>\n",
+        channel_title
+    ));
+
+    let base_path = get_base_path(&state, Some(&id)).await;
+
+    for (rel_path, view) in items {
+        let enclosure_url = format!(
+            "/api/libraries/{}/content/{}",
+            id,
+            urlencoding::encode(&rel_path)
+        );
+
+        let technical = match &base_path {
+            Some(root) => crate::metadata::read_local_metadata(&root.join(&rel_path))
+                .await
+                .and_then(|meta| meta.technical)
+                .and_then(|technical| technical.duration_secs),
+            None => None,
+        };
+
+        xml.push_str("<item>\n");
+        xml.push_str(&format!(
+            "<title>{}</title>\n",
+            escape_xml(&view.display_name)
+        ));
+        xml.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            escape_xml(&rel_path)
+        ));
+        xml.push_str(&format!(
+            "<enclosure url=\"{}\" type=\"video/mp4\"/>\n",
+            escape_xml(&enclosure_url)
+        ));
+        if let Some(poster_url) = &view.poster_url {
+            xml.push_str(&format!(
+                "<itunes:image href=\"{}\"/>\n",
+                escape_xml(poster_url)
+            ));
+        }
+        if let Some(duration_secs) = technical {
+            xml.push_str(&format!(
+                "<itunes:duration>{}</itunes:duration>\n",
+                duration_secs.round() as i64
+            ));
+        }
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+
+    (
+        [(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")],
+        xml,
+    )
+        .into_response()
+}
+
+/// Recursively walks `get_files_for_ui`, flattening every non-directory
+/// entry into `out` as `(relative_path, view)`. Boxed since async fns can't
+/// recurse directly.
+fn collect_items<'a>(
+    state: &'a AppState,
+    lib_id: &'a str,
+    path: String,
+    out: &'a mut Vec<(String, FileView)>,
+) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+    Box::pin(async move {
+        let entries = get_files_for_ui(state, lib_id, &path).await;
+        for entry in entries {
+            let rel_path = if path.is_empty() {
+                entry.name.clone()
+            } else {
+                format!("{}/{}", path, entry.name)
+            };
+
+            if entry.is_dir {
+                collect_items(state, lib_id, rel_path, out).await;
+            } else {
+                out.push((rel_path, entry));
+            }
+        }
+    })
+}
+
+/// Escapes the five XML-significant characters for safe use in both text
+/// content and attribute values.
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
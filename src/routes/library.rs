@@ -1,14 +1,16 @@
 use axum::{
     Json,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode, header},
     response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::models::{AppState, Library, LibraryType};
+use crate::metadata::{cleanup_filename, read_local_metadata};
+use crate::models::{AppState, Library, LibraryType, StoreBackend};
+use crate::organizer::{OrganizeTarget, organize_file};
 
 const LIBRARIES_FILE: &str = "libraries.json";
 
@@ -17,6 +19,16 @@ pub struct CreateLibraryPayload {
     pub name: String,
     pub path: String,
     pub kind: LibraryType,
+    #[serde(default)]
+    pub backend: StoreBackend,
+    #[serde(default)]
+    pub hide_clutter: bool,
+    #[serde(default)]
+    pub clutter_extra_patterns: Vec<String>,
+    #[serde(default)]
+    pub movie_format_template: Option<String>,
+    #[serde(default)]
+    pub episode_format_template: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -36,6 +48,17 @@ pub async fn get_libraries(State(state): State<AppState>) -> impl IntoResponse {
     Json(libraries.clone()).into_response()
 }
 
+/// Loads the library list `models::AppState.libraries` starts from, reading
+/// `LIBRARIES_FILE` the same way `create_library`/`delete_library` persist
+/// it. An empty `Vec` (no libraries configured yet) if the file is missing
+/// or fails to parse, rather than failing startup over it.
+pub(crate) async fn load_libraries() -> Vec<Library> {
+    match tokio::fs::read_to_string(LIBRARIES_FILE).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
 pub async fn create_library(
     State(state): State<AppState>,
     Json(payload): Json<CreateLibraryPayload>,
@@ -48,6 +71,11 @@ pub async fn create_library(
         name: payload.name,
         path: PathBuf::from(payload.path),
         kind: payload.kind,
+        backend: payload.backend,
+        hide_clutter: payload.hide_clutter,
+        clutter_extra_patterns: payload.clutter_extra_patterns,
+        movie_format_template: payload.movie_format_template,
+        episode_format_template: payload.episode_format_template,
     };
 
     libraries.push(library);
@@ -80,6 +108,171 @@ pub async fn delete_library(
     StatusCode::NOT_FOUND.into_response()
 }
 
+/// Kicks off a background scan for library `id`, using the library id as
+/// the job id -- `ScanJobStore` is already keyed that way, so there's no
+/// separate job-id scheme to invent. Returns 409 if a scan for this library
+/// is already in progress (`Scanner::scan_library`'s per-library lock).
+///
+/// Intended to be layered behind `auth::require_admin`, like the rest of
+/// the library-management routes.
+pub async fn scan_library(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let lib = {
+        let libraries = state.libraries.read().await;
+        match libraries.iter().find(|l| l.id == id) {
+            Some(lib) => lib.clone(),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        }
+    };
+
+    if state.scanner.scan_library(&lib).await {
+        StatusCode::ACCEPTED.into_response()
+    } else {
+        StatusCode::CONFLICT.into_response()
+    }
+}
+
+/// Reports the checkpointed progress of library `id`'s scan job (state,
+/// files scanned/matched/failed), for a UI to poll while `scan_library`
+/// runs in the background.
+///
+/// Intended to be layered behind `auth::require_admin`, like the rest of
+/// the library-management routes.
+pub async fn scan_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match state.scanner.job_status(&id).await {
+        Some(job) => Json(job).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OrganizeParams {
+    pub path: String,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Walks up from `path`'s parent toward `library_root`, returning the title
+/// of the first `LocalMetadata` sidecar found that doesn't look like a
+/// season folder (mirrors the parent-walk in `routes::video::lookup_metadata`,
+/// since `LocalMetadata` has no dedicated show-title field of its own).
+async fn find_show_title(path: &std::path::Path, library_root: &std::path::Path) -> Option<String> {
+    let season_title_re = regex::Regex::new(r"(?i)^season\s*\d+$").unwrap();
+    let mut current = path.parent();
+    while let Some(p) = current {
+        if p == library_root {
+            break;
+        }
+        if let Some(meta) = read_local_metadata(p).await {
+            let title_lower = meta.title.to_lowercase();
+            if !season_title_re.is_match(&meta.title) && title_lower != "specials" {
+                return Some(meta.title);
+            }
+        }
+        current = p.parent();
+    }
+    None
+}
+
+/// Relocates library `id`'s already-matched file at `path` (i.e. one with a
+/// `{file}.json` sidecar, as written by `routes::video::lookup_metadata`)
+/// into `AppConfig::organized_library_dir`, per the library's
+/// `movie_format_template`/`episode_format_template` (falling back to
+/// `organizer::DEFAULT_MOVIE_TEMPLATE`/`DEFAULT_EPISODE_TEMPLATE`) and
+/// `AppConfig::organize_action`/`organize_conflict`. `dry_run=true` returns
+/// the planned move without touching the filesystem.
+///
+/// Intended to be layered behind `auth::require_admin`, like the rest of
+/// the library-management routes.
+pub async fn organize_path(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<OrganizeParams>,
+) -> impl IntoResponse {
+    let lib = {
+        let libraries = state.libraries.read().await;
+        match libraries.iter().find(|l| l.id == id) {
+            Some(lib) => lib.clone(),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        }
+    };
+
+    let clean_path = params.path.trim_start_matches('/');
+    let abs_path = lib.path.join(clean_path);
+    if !abs_path.is_file() {
+        return (StatusCode::BAD_REQUEST, "Not a file").into_response();
+    }
+
+    let Some(meta) = read_local_metadata(&abs_path).await else {
+        return (StatusCode::NOT_FOUND, "No matched metadata for this file").into_response();
+    };
+
+    let file_name = abs_path.file_name().unwrap().to_string_lossy().to_string();
+
+    let (target, template) = if lib.kind == LibraryType::TVShows {
+        let parsed = crate::matcher::match_filename(&file_name, None, false);
+        let (Some(season), Some(episode)) = (parsed.season, parsed.episode) else {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Could not determine season/episode from filename",
+            )
+                .into_response();
+        };
+        let show_title = find_show_title(&abs_path, &lib.path)
+            .await
+            .unwrap_or(parsed.title);
+
+        let target = OrganizeTarget::Episode {
+            show_title,
+            season,
+            episode,
+            episode_title: meta.title,
+            special: season == 0,
+        };
+        let template = lib
+            .episode_format_template
+            .clone()
+            .unwrap_or_else(|| crate::organizer::DEFAULT_EPISODE_TEMPLATE.to_string());
+        (target, template)
+    } else {
+        let (_, year, _) = cleanup_filename(&file_name);
+        let target = OrganizeTarget::Movie {
+            title: meta.title,
+            year,
+        };
+        let template = lib
+            .movie_format_template
+            .clone()
+            .unwrap_or_else(|| crate::organizer::DEFAULT_MOVIE_TEMPLATE.to_string());
+        (target, template)
+    };
+
+    let result = organize_file(
+        &abs_path,
+        &target,
+        &state.config.organized_library_dir,
+        &template,
+        state.config.organize_action,
+        state.config.organize_conflict,
+        params.dry_run,
+    )
+    .await;
+
+    match result {
+        Ok(Some(plan)) => Json(plan).into_response(),
+        Ok(None) => StatusCode::CONFLICT.into_response(),
+        Err(e) => {
+            eprintln!("Failed to organize {:?}: {}", abs_path, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 pub async fn browse_filesystem(Query(params): Query<BrowseParams>) -> impl IntoResponse {
     let path = if let Some(p) = params.path.filter(|s| !s.is_empty()) {
         PathBuf::from(p)
@@ -143,36 +336,146 @@ pub async fn browse_filesystem(Query(params): Query<BrowseParams>) -> impl IntoR
     Json(entries).into_response()
 }
 
+/// Result of matching a `Range` request header against the file's total size.
+enum RangeRequest {
+    /// No (usable) Range header was present; serve the whole file.
+    Full,
+    /// A single satisfiable range, inclusive start/end byte offsets.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parses a `Range: bytes=...` header value against `total` file bytes.
+/// Only single-range requests are supported (the common case for media
+/// players); anything else is treated as if no Range header was sent.
+fn parse_range(header_value: &str, total: u64) -> RangeRequest {
+    let Some(spec) = header_value.strip_prefix("bytes=") else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start_str, end_str)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+
+    if start_str.is_empty() {
+        // Suffix range: bytes=-500 -> last 500 bytes
+        return match end_str.parse::<u64>() {
+            Ok(suffix_len) if suffix_len > 0 && total > 0 => {
+                let start = total.saturating_sub(suffix_len);
+                RangeRequest::Partial(start, total - 1)
+            }
+            _ => RangeRequest::Unsatisfiable,
+        };
+    }
+
+    let Ok(start) = start_str.parse::<u64>() else {
+        return RangeRequest::Unsatisfiable;
+    };
+    if start >= total {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    let end = if end_str.is_empty() {
+        total - 1
+    } else {
+        match end_str.parse::<u64>() {
+            Ok(e) => e.min(total.saturating_sub(1)),
+            Err(_) => return RangeRequest::Unsatisfiable,
+        }
+    };
+
+    if end < start {
+        return RangeRequest::Unsatisfiable;
+    }
+
+    RangeRequest::Partial(start, end)
+}
+
 pub async fn serve_content(
     State(state): State<AppState>,
     Path((id, file_path)): Path<(String, String)>,
+    headers: HeaderMap,
 ) -> impl IntoResponse {
-    let libraries = state.libraries.read().await;
+    let lib = {
+        let libraries = state.libraries.read().await;
+        match libraries.iter().find(|l| l.id == id) {
+            Some(lib) => lib.clone(),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        }
+    };
 
-    if let Some(lib) = libraries.iter().find(|l| l.id == id) {
-        let mut full_path = lib.path.clone();
-        // Remove leading slash from file_path if present to avoid replacing root
-        let clean_path = file_path.trim_start_matches('/');
-        full_path.push(clean_path);
-
-        // Security check: ensure we are taking about a file inside the library
-        if full_path.exists() && full_path.starts_with(&lib.path) {
-            // Simple mime guessing
-            let mime = mime_guess::from_path(&full_path).first_or_octet_stream();
-
-            if let Ok(file) = tokio::fs::File::open(full_path).await {
-                let stream = tokio_util::io::ReaderStream::new(file);
-                let body = axum::body::Body::from_stream(stream);
-
-                return (
-                    StatusCode::OK,
-                    [(axum::http::header::CONTENT_TYPE, mime.as_ref())],
-                    body,
-                )
-                    .into_response();
+    let clean_path = file_path.trim_start_matches('/');
+
+    if lib.kind == LibraryType::Remote {
+        return match crate::remote::resolve_direct_url(clean_path).await {
+            Ok(direct_url) => axum::response::Redirect::temporary(&direct_url).into_response(),
+            Err(e) => {
+                eprintln!(
+                    "Failed to resolve remote entry {} in library {}: {}",
+                    clean_path, id, e
+                );
+                StatusCode::BAD_GATEWAY.into_response()
             }
+        };
+    }
+
+    let store = lib.store().await;
+
+    let total = match store.metadata(clean_path).await {
+        Ok(meta) => meta.size,
+        Err(e) => {
+            eprintln!("Failed to stat {} in library {}: {}", clean_path, id, e);
+            return StatusCode::NOT_FOUND.into_response();
+        }
+    };
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| parse_range(v, total))
+        .unwrap_or(RangeRequest::Full);
+
+    let (status, byte_range, content_range) = match range {
+        RangeRequest::Unsatisfiable => {
+            return (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", total))],
+            )
+                .into_response();
+        }
+        RangeRequest::Full => (StatusCode::OK, None, None),
+        RangeRequest::Partial(start, end) => (
+            StatusCode::PARTIAL_CONTENT,
+            Some(start..end + 1),
+            Some(format!("bytes {}-{}/{}", start, end, total)),
+        ),
+    };
+
+    let content_length = byte_range
+        .as_ref()
+        .map(|r| r.end - r.start)
+        .unwrap_or(total);
+
+    let stream = match store.open_range(clean_path, byte_range).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("Failed to open {} in library {}: {}", clean_path, id, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
+    };
+    let body = axum::body::Body::from_stream(stream);
+
+    let mime = mime_guess::from_path(clean_path).first_or_octet_stream();
+    let mut response_headers = vec![
+        (header::CONTENT_TYPE, mime.as_ref().to_string()),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::CONTENT_LENGTH, content_length.to_string()),
+    ];
+    if let Some(content_range) = content_range {
+        response_headers.push((header::CONTENT_RANGE, content_range));
     }
 
-    StatusCode::NOT_FOUND.into_response()
+    (status, response_headers, body).into_response()
 }
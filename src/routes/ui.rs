@@ -5,11 +5,10 @@ use axum::{
     response::{IntoResponse, Redirect, Response},
 };
 use axum_extra::extract::cookie::CookieJar;
-use jsonwebtoken::{DecodingKey, Validation, decode};
 use serde::Deserialize;
 
 use crate::{
-    auth::{COOKIE_NAME, Claims, JWT_SECRET},
+    auth::{COOKIE_NAME, verify_session_cookie},
     models::AppState,
     routes::video::get_base_path,
 };
@@ -75,17 +74,10 @@ pub async fn index_handler(
     Query(params): Query<IndexParams>,
 ) -> Response {
     // 1. Check Auth (Cookie)
-    let user_data = if let Some(token) = jar.get(COOKIE_NAME) {
-        let validation = Validation::default();
-        if let Ok(data) = decode::<Claims>(
-            token.value(),
-            &DecodingKey::from_secret(JWT_SECRET),
-            &validation,
-        ) {
-            data.claims
-        } else {
-            return Redirect::to("/login.html").into_response();
-        }
+    let user_data = if let Some(token) = jar.get(COOKIE_NAME)
+        && let Some(claims) = verify_session_cookie(token.value(), &state.auth.config)
+    {
+        claims
     } else {
         return Redirect::to("/login.html").into_response();
     };
@@ -184,6 +176,8 @@ pub async fn index_handler(
                 let image = match l.kind {
                     crate::models::LibraryType::Movies => "/library_movies.png",
                     crate::models::LibraryType::TVShows => "/library_tv.png",
+                    crate::models::LibraryType::Anime => "/library_tv.png",
+                    crate::models::LibraryType::Remote => "/library_remote.png",
                     crate::models::LibraryType::Other => "/library_other.png",
                 };
                 LibraryView {
@@ -209,7 +203,21 @@ pub async fn index_handler(
     }
 }
 
-async fn get_files_for_ui(state: &AppState, lib_id: &str, path: &str) -> Vec<FileView> {
+/// `pub(crate)` so `routes::feed::feed_handler` can reuse the same
+/// directory/remote-listing traversal for its `<item>` enumeration instead
+/// of duplicating it.
+pub(crate) async fn get_files_for_ui(state: &AppState, lib_id: &str, path: &str) -> Vec<FileView> {
+    let lib_kind = {
+        let libraries = state.libraries.read().await;
+        libraries
+            .iter()
+            .find(|l| l.id == lib_id)
+            .map(|l| l.kind.clone())
+    };
+    if lib_kind == Some(crate::models::LibraryType::Remote) {
+        return get_remote_files_for_ui(state, lib_id).await;
+    }
+
     // Logic adapted from video::list_files
     let Some(base_path) = get_base_path(state, Some(lib_id)).await else {
         return vec![];
@@ -251,7 +259,12 @@ async fn get_files_for_ui(state: &AppState, lib_id: &str, path: &str) -> Vec<Fil
             let path_encoded = urlencoding::encode(&rel_path).to_string();
 
             if is_dir {
-                let mut display = file_name.clone();
+                let parsed = crate::metadata::parse_filename(&file_name);
+                let mut display = if parsed.title.is_empty() {
+                    file_name.clone()
+                } else {
+                    parsed.title
+                };
                 let mut poster_url = None;
 
                 // Metadata check
@@ -300,7 +313,12 @@ async fn get_files_for_ui(state: &AppState, lib_id: &str, path: &str) -> Vec<Fil
                     ext.as_str(),
                     "mp4" | "mkv" | "avi" | "mov" | "webm" | "m4v" | "flv" | "wmv"
                 ) {
-                    let mut display = file_name.clone();
+                    let parsed = crate::metadata::parse_filename(&file_name);
+                    let mut display = if parsed.title.is_empty() {
+                        file_name.clone()
+                    } else {
+                        parsed.title
+                    };
                     let mut poster_url = None;
 
                     let item_path = canonical_path.join(&file_name);
@@ -356,6 +374,43 @@ async fn get_files_for_ui(state: &AppState, lib_id: &str, path: &str) -> Vec<Fil
     entries
 }
 
+/// `get_files_for_ui`'s counterpart for `LibraryType::Remote` libraries:
+/// lists a playlist/channel's entries via `remote::list_entries` instead of
+/// reading a directory. Always flat (no sub-folders), and `path_encoded`
+/// carries the entry id rather than a relative filesystem path --
+/// `routes::library::serve_content` passes it straight to
+/// `remote::resolve_direct_url`.
+async fn get_remote_files_for_ui(state: &AppState, lib_id: &str) -> Vec<FileView> {
+    let Some(url) = get_base_path(state, Some(lib_id))
+        .await
+        .map(|p| p.to_string_lossy().to_string())
+    else {
+        return vec![];
+    };
+
+    let entries = match crate::remote::list_entries(&url).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "[remote] Failed to list entries for library {}: {}",
+                lib_id, e
+            );
+            return vec![];
+        }
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| FileView {
+            name: entry.title.clone(),
+            display_name: entry.title,
+            path_encoded: urlencoding::encode(&entry.id).to_string(),
+            is_dir: false,
+            poster_url: entry.thumbnail_url,
+        })
+        .collect()
+}
+
 #[derive(Template)]
 #[template(path = "player.html")]
 pub struct PlayerTemplate {
@@ -385,26 +440,15 @@ pub struct WatchParams {
 }
 
 pub async fn watch_handler(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     jar: CookieJar,
     axum::Form(params): axum::Form<WatchParams>,
 ) -> Response {
-    // 1. Check Auth (Cookie)
-    if jar.get(COOKIE_NAME).is_none() {
-        return Redirect::to("/login.html").into_response();
-    }
-    // Verify token:
-    let logged_in = if let Some(token) = jar.get(COOKIE_NAME) {
-        let validation = Validation::default();
-        decode::<Claims>(
-            token.value(),
-            &DecodingKey::from_secret(JWT_SECRET),
-            &validation,
-        )
-        .is_ok()
-    } else {
-        false
-    };
+    // Check Auth (Cookie)
+    let logged_in = jar
+        .get(COOKIE_NAME)
+        .and_then(|token| verify_session_cookie(token.value(), &state.auth.config))
+        .is_some();
 
     if !logged_in {
         return Redirect::to("/login.html").into_response();
@@ -1,23 +1,28 @@
 use axum::{
     body::Body,
-    extract::{Json, Query, State},
+    extract::{Json, Path as AxumPath, Query, State},
     http::StatusCode,
     response::{IntoResponse, Response},
 };
 use regex::Regex;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio_util::io::ReaderStream;
+use uuid::Uuid;
 
 use crate::metadata::{
     LocalMetadata, cleanup_filename, download_image, fetch_tmdb_metadata,
     fetch_tmdb_season_metadata, read_local_metadata, save_local_metadata,
 };
 use crate::models::{
-    AppState, FileEntry, ListParams, LookupParams, MetadataParams, StreamParams, SubtitleParams,
+    AppState, FileEntry, HlsStartParams, HlsStartResponse, ListParams, LookupParams,
+    MetadataParams, ProgressParams, StreamParams, SubtitleListing, SubtitleParams, TaskKind,
+    ThumbnailParams,
 };
-use crate::streaming::{
-    ProcessStream, extract_subtitle, find_keyframe, probe_metadata, spawn_ffmpeg,
+use crate::streaming::process::{
+    InputSource, ProcessStream, discover_external_subtitles, extract_external_subtitle,
+    extract_subtitle, probe_metadata, probe_streams, spawn_ffmpeg,
 };
+use crate::streaming::profile::resolve_profile;
 
 // ...
 
@@ -34,6 +39,14 @@ pub async fn get_base_path(
     None
 }
 
+/// Like `get_base_path`, but returns the full `Library` so callers can read
+/// per-library settings (e.g. `hide_clutter`) alongside the path.
+async fn get_library(state: &AppState, library_id: Option<&str>) -> Option<crate::models::Library> {
+    let id = library_id?;
+    let libraries = state.libraries.read().await;
+    libraries.iter().find(|l| l.id == id).cloned()
+}
+
 pub async fn list_files(
     State(state): State<AppState>,
     Query(params): Query<ListParams>,
@@ -43,9 +56,10 @@ pub async fn list_files(
         return Json(Vec::<FileEntry>::new()).into_response();
     }
 
-    let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
+    let Some(library) = get_library(&state, params.library_id.as_deref()).await else {
         return (StatusCode::BAD_REQUEST, "Library not found").into_response();
     };
+    let base_path = library.path.clone();
 
     let mut abs_path = base_path.clone();
     abs_path.push(&params.path);
@@ -84,6 +98,12 @@ pub async fn list_files(
             rel_path.push_str(&file_name);
 
             if is_dir {
+                if library.hide_clutter
+                    && crate::matcher::is_clutter_name(&file_name, &library.clutter_extra_patterns)
+                {
+                    continue;
+                }
+
                 let mut title = None;
                 let mut poster = None;
 
@@ -112,6 +132,17 @@ pub async fn list_files(
             {
                 match ext.to_lowercase().as_str() {
                     "mp4" | "mkv" | "avi" | "mov" | "webm" | "m4v" | "flv" | "wmv" => {
+                        if library.hide_clutter {
+                            let size = entry.metadata().await.map(|m| m.len()).unwrap_or(u64::MAX);
+                            if crate::matcher::is_clutter(
+                                &file_name,
+                                size,
+                                &library.clutter_extra_patterns,
+                            ) {
+                                continue;
+                            }
+                        }
+
                         let mut title = None;
                         let mut poster = None;
 
@@ -172,8 +203,8 @@ pub async fn get_metadata(
         return StatusCode::NOT_FOUND.into_response();
     }
 
-    match probe_metadata(&abs_path).await {
-        Ok(mut metadata) => {
+    match probe_metadata(InputSource::File(abs_path.clone())).await {
+        Ok((mut metadata, _input)) => {
             // Check for sidecar JSON
             if let Some(meta) = read_local_metadata(&abs_path).await
                 && !meta.title.is_empty()
@@ -189,6 +220,62 @@ pub async fn get_metadata(
     }
 }
 
+/// Full ffprobe stream inventory (video/audio/subtitle), so clients can show
+/// language/title/default before picking a track rather than guessing from
+/// the zero-based re-indexed summaries in `get_metadata`.
+pub async fn get_streams(
+    State(state): State<AppState>,
+    Query(params): Query<MetadataParams>,
+) -> impl IntoResponse {
+    let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let abs_path = base_path.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match probe_streams(&abs_path).await {
+        Ok(streams) => Json(streams).into_response(),
+        Err(e) => {
+            eprintln!("Stream inventory probe failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// JPEG poster frame at `timestamp`, for scrubbing-preview UIs and chapter
+/// thumbnails -- see `streaming::process::extract_thumbnail`.
+pub async fn get_thumbnail(
+    State(state): State<AppState>,
+    Query(params): Query<ThumbnailParams>,
+) -> impl IntoResponse {
+    let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let abs_path = base_path.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    match state
+        .hls
+        .extract_thumbnail(&abs_path, params.timestamp)
+        .await
+    {
+        Ok(bytes) => (
+            StatusCode::OK,
+            [(axum::http::header::CONTENT_TYPE, "image/jpeg")],
+            bytes,
+        )
+            .into_response(),
+        Err(e) => {
+            eprintln!("Thumbnail extraction failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
 pub async fn stream_video(
     State(state): State<AppState>,
     Query(params): Query<StreamParams>,
@@ -202,8 +289,8 @@ pub async fn stream_video(
     }
 
     // Detect codec & audio & duration via unified probe
-    let metadata = match probe_metadata(&abs_path).await {
-        Ok(m) => m,
+    let metadata = match probe_metadata(InputSource::File(abs_path.clone())).await {
+        Ok((m, _input)) => m,
         Err(e) => {
             eprintln!("Probe failed: {}", e);
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -216,7 +303,7 @@ pub async fn stream_video(
 
     let mut actual_start = params.start;
     if actual_start > 0.0 {
-        match find_keyframe(&abs_path, actual_start).await {
+        match state.hls.find_keyframe(&abs_path, actual_start).await {
             Ok(k) => {
                 actual_start = k;
             }
@@ -245,12 +332,39 @@ pub async fn stream_video(
         None
     };
 
-    match spawn_ffmpeg(&abs_path, actual_start, audio_track_idx, &codec_name) {
+    let supported_codecs: Vec<String> = params
+        .supported_codecs
+        .as_deref()
+        .map(|raw| {
+            raw.split(',')
+                .map(|c| c.trim().to_lowercase())
+                .filter(|c| !c.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    let profile = resolve_profile(&codec_name, &supported_codecs);
+
+    match spawn_ffmpeg(
+        InputSource::File(abs_path.clone()),
+        actual_start,
+        audio_track_idx,
+        &codec_name,
+        profile,
+    ) {
         Ok(mut child) => {
             let stdout = child.stdout.take().unwrap();
             let stderr = child.stderr.take().unwrap();
 
-            // Spawn stderr logger
+            // Tracked under its own task id so `get_progress` can report
+            // position/speed while this stream is live, and so
+            // `TranscodeManager`'s reaper can kill it if ffmpeg stalls.
+            let task_id = Uuid::new_v4();
+            let task_key = crate::streaming::manager::TaskKey::Stream(task_id);
+            if let Some(pid) = child.id() {
+                state.hls.register_progress(task_key, pid).await;
+            }
+
+            let progress_manager = state.hls.clone();
             tokio::spawn(async move {
                 let mut reader = BufReader::new(stderr);
                 let mut line = String::new();
@@ -258,9 +372,10 @@ pub async fn stream_video(
                     if n == 0 {
                         break;
                     }
-                    // eprint!("[ffmpeg] {}", line); // silenced logging
+                    progress_manager.handle_progress_line(task_key, &line).await;
                     line.clear();
                 }
+                progress_manager.remove_progress(task_key).await;
             });
 
             let stream = ReaderStream::new(stdout);
@@ -269,10 +384,11 @@ pub async fn stream_video(
             Response::builder()
                 .header("Content-Type", "video/mp4")
                 .header("Cache-Control", "no-cache")
-                .header("X-Video-Codec", codec_name)
+                .header("X-Video-Codec", profile.target_codec)
                 .header("X-Has-Audio", if has_audio { "true" } else { "false" })
                 .header("X-Video-Duration", duration.to_string())
                 .header("X-Actual-Start", actual_start.to_string())
+                .header("X-Sratim-Task-Id", task_id.to_string())
                 // No Content-Length, implies chunked if body is a stream
                 .body(Body::from_stream(process_stream))
                 .unwrap()
@@ -284,9 +400,15 @@ pub async fn stream_video(
     }
 }
 
-pub async fn get_subtitles(
+/// Starts an HLS-segmented transcode of `params.path` and returns its
+/// session id. Poll `GET /api/hls/{session_id}/playlist.m3u8` once started;
+/// ffmpeg writes segments as it goes, so the playlist fills in
+/// progressively. Unlike `stream_video`, the session outlives this request
+/// -- `state.hls` (a `streaming::manager::TranscodeManager`) keeps it alive
+/// until the client stops polling or calls `stop_hls`.
+pub async fn start_hls(
     State(state): State<AppState>,
-    Query(params): Query<SubtitleParams>,
+    Query(params): Query<HlsStartParams>,
 ) -> impl IntoResponse {
     let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
         return StatusCode::NOT_FOUND.into_response();
@@ -296,38 +418,276 @@ pub async fn get_subtitles(
         return StatusCode::NOT_FOUND.into_response();
     }
 
-    match extract_subtitle(&abs_path, params.index) {
-        Ok(mut child) => {
-            let stdout = child.stdout.take().unwrap();
-            let stderr = child.stderr.take().unwrap();
+    let video_codec = match probe_metadata(InputSource::File(abs_path.clone())).await {
+        Ok((metadata, _input)) => metadata.video_codec,
+        Err(e) => {
+            eprintln!("Probe failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
 
-            // Spawn stderr logger
-            tokio::spawn(async move {
-                let mut reader = BufReader::new(stderr);
-                let mut line = String::new();
-                while let Ok(n) = reader.read_line(&mut line).await {
-                    if n == 0 {
-                        break;
-                    }
-                    eprint!("[ffmpeg-sub] {}", line);
-                    line.clear();
-                }
-            });
+    match state
+        .hls
+        .start_hls(
+            &abs_path,
+            params.start,
+            params.audio_track,
+            &video_codec,
+            &state.hls_temp_dir,
+        )
+        .await
+    {
+        Ok(session_id) => Json(HlsStartResponse { session_id }).into_response(),
+        Err(e) => {
+            eprintln!("Failed to start HLS session: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
 
-            let stream = ReaderStream::new(stdout);
-            let process_stream = ProcessStream::new(stream, child);
+/// Serves the growing media playlist ffmpeg is (or was) writing for
+/// `session_id`, and records that the client is still watching.
+pub async fn get_hls_playlist(
+    State(state): State<AppState>,
+    AxumPath(session_id): AxumPath<Uuid>,
+) -> impl IntoResponse {
+    let Some(path) = state.hls.playlist_path(session_id).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
 
-            Response::builder()
-                .header("Content-Type", "text/vtt")
-                .header("Cache-Control", "no-cache")
-                .body(Body::from_stream(process_stream))
-                .unwrap()
+    match tokio::fs::read_to_string(&path).await {
+        Ok(body) => {
+            state.hls.touch(session_id, 0).await;
+            (
+                StatusCode::OK,
+                [(
+                    axum::http::header::CONTENT_TYPE,
+                    "application/vnd.apple.mpegurl",
+                )],
+                body,
+            )
+                .into_response()
+        }
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Serves one `init.mp4`/`segmentNNNNN.m4s` file for `session_id`, touching
+/// the session's idle timeout. Segment names are ffmpeg-generated; anything
+/// else is rejected so the path param can't be used to escape the session
+/// dir.
+pub async fn get_hls_segment(
+    State(state): State<AppState>,
+    AxumPath((session_id, segment)): AxumPath<(Uuid, String)>,
+) -> impl IntoResponse {
+    let is_safe_segment_name = !segment.is_empty()
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !is_safe_segment_name {
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    let Some(path) = state.hls.segment_path(session_id, &segment).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match tokio::fs::read(&path).await {
+        Ok(bytes) => {
+            let segment_num = segment
+                .trim_start_matches("segment")
+                .trim_end_matches(".m4s")
+                .parse::<u64>()
+                .unwrap_or(0);
+            state.hls.touch(session_id, segment_num).await;
+            (
+                StatusCode::OK,
+                [(axum::http::header::CONTENT_TYPE, "video/iso.segment")],
+                bytes,
+            )
                 .into_response()
         }
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct StopHlsParams {
+    pub session_id: Uuid,
+}
+
+/// Stops an HLS session early, killing its ffmpeg child and purging its
+/// temp dir immediately instead of waiting for the reaper's idle timeout.
+pub async fn stop_hls(
+    State(state): State<AppState>,
+    Json(params): Json<StopHlsParams>,
+) -> impl IntoResponse {
+    if state.hls.stop(params.session_id).await {
+        StatusCode::OK
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+/// Lists every subtitle available for a path -- embedded tracks (from
+/// `probe_metadata`, with codec/language/title) and external sidecar files
+/// next to the video -- so a client can build one subtitle menu instead of
+/// guessing indices.
+pub async fn list_subtitles(
+    State(state): State<AppState>,
+    Query(params): Query<MetadataParams>,
+) -> impl IntoResponse {
+    let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let abs_path = base_path.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let embedded = match probe_metadata(InputSource::File(abs_path.clone())).await {
+        Ok((metadata, _input)) => metadata.subtitle_tracks,
         Err(e) => {
-            eprintln!("Failed to extract subtitles: {}", e);
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            eprintln!("Subtitle probe failed: {}", e);
+            Vec::new()
+        }
+    };
+    let external = discover_external_subtitles(&abs_path);
+
+    Json(SubtitleListing { embedded, external }).into_response()
+}
+
+/// Serves one subtitle as WebVTT, either an embedded stream (`index`,
+/// converted via `extract_subtitle`) or an external sidecar file (`file`,
+/// converted via `extract_external_subtitle`); `file` takes precedence when
+/// both are set. Sets `Content-Language` from whichever track/sidecar
+/// carries a language tag.
+pub async fn get_subtitles(
+    State(state): State<AppState>,
+    Query(params): Query<SubtitleParams>,
+) -> impl IntoResponse {
+    let Some(base_path) = get_base_path(&state, params.library_id.as_deref()).await else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let abs_path = base_path.join(&params.path);
+    if !abs_path.exists() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let (child, language, task_key) = if let Some(file_name) = params.file.as_deref() {
+        // Bare filename only -- no path traversal via `../` or an absolute path.
+        if file_name.contains('/') || file_name.contains('\\') {
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+        let Some(parent) = abs_path.parent() else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+        let sub_path = parent.join(file_name);
+        if !sub_path.exists() {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+
+        let language = discover_external_subtitles(&abs_path)
+            .into_iter()
+            .find(|s| s.filename == file_name)
+            .and_then(|s| s.language);
+
+        match extract_external_subtitle(&sub_path) {
+            // No `-progress` reports from this one (unlike `extract_subtitle`,
+            // it isn't built with pipe:2 progress args), so nothing to track.
+            Ok(child) => (child, language, None),
+            Err(e) => {
+                eprintln!("Failed to extract external subtitle: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    } else {
+        let Some(index) = params.index else {
+            return StatusCode::BAD_REQUEST.into_response();
+        };
+
+        let language = probe_metadata(InputSource::File(abs_path.clone()))
+            .await
+            .ok()
+            .and_then(|(m, _input)| m.subtitle_tracks.into_iter().find(|t| t.index == index))
+            .and_then(|t| t.language);
+
+        match extract_subtitle(InputSource::File(abs_path.clone()), index) {
+            Ok(child) => {
+                let key = crate::streaming::manager::TaskKey::Subtitles(Uuid::new_v4());
+                (child, language, Some(key))
+            }
+            Err(e) => {
+                eprintln!("Failed to extract subtitles: {}", e);
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        }
+    };
+
+    let mut child = child;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    if let Some(key) = task_key
+        && let Some(pid) = child.id()
+    {
+        state.hls.register_progress(key, pid).await;
+    }
+
+    let progress_manager = state.hls.clone();
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stderr);
+        let mut line = String::new();
+        while let Ok(n) = reader.read_line(&mut line).await {
+            if n == 0 {
+                break;
+            }
+            match task_key {
+                Some(key) => progress_manager.handle_progress_line(key, &line).await,
+                None => eprint!("[ffmpeg-sub] {}", line),
+            }
+            line.clear();
+        }
+        if let Some(key) = task_key {
+            progress_manager.remove_progress(key).await;
         }
+    });
+
+    let stream = ReaderStream::new(stdout);
+    let process_stream = ProcessStream::new(stream, child);
+
+    let mut builder = Response::builder()
+        .header("Content-Type", "text/vtt")
+        .header("Cache-Control", "no-cache");
+    if let Some(language) = language {
+        builder = builder.header("Content-Language", language);
+    }
+    if let Some(crate::streaming::manager::TaskKey::Subtitles(id)) = task_key {
+        builder = builder.header("X-Sratim-Task-Id", id.to_string());
+    }
+
+    builder
+        .body(Body::from_stream(process_stream))
+        .unwrap()
+        .into_response()
+}
+
+/// Reports the latest parsed `-progress` snapshot for a task id handed out
+/// via `X-Sratim-Task-Id` on `stream_video`/`get_subtitles`, so a client can
+/// show current position/speed for its own active stream. `404` once the
+/// task has finished (or was never tracked, e.g. an external subtitle).
+pub async fn get_progress(
+    State(state): State<AppState>,
+    Query(params): Query<ProgressParams>,
+) -> impl IntoResponse {
+    let key = match params.kind {
+        TaskKind::Stream => crate::streaming::manager::TaskKey::Stream(params.id),
+        TaskKind::Subtitles => crate::streaming::manager::TaskKey::Subtitles(params.id),
+    };
+
+    match state.hls.progress_stats(key).await {
+        Some(stats) => Json(stats).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
     }
 }
 
@@ -520,7 +880,7 @@ pub async fn lookup_metadata(
     // 2. Fallback: Probe internal title (only for files)
     if best_match.is_none() && !is_dir {
         println!("No match for filename, probing internal title...");
-        if let Ok(meta) = probe_metadata(&abs_path).await {
+        if let Ok((meta, _input)) = probe_metadata(InputSource::File(abs_path.clone())).await {
             if let Some(internal_title) = meta.title {
                 println!("Internal title found: {}", internal_title);
                 let (clean_int_title, int_year) = cleanup_filename(&internal_title);
@@ -0,0 +1,184 @@
+//! Persisted, resumable scan jobs. `Scanner::scan_library` used to be a
+//! fire-and-forget `tokio::spawn` with no handle and no memory of prior
+//! progress -- a restart re-walked every directory from scratch. This
+//! checkpoints, per library, which directories have been fully walked and
+//! which file paths already produced metadata, so a resumed scan can skip
+//! what's already done, and exposes job state/progress for a UI.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+const SCAN_JOBS_FILE: &str = "scan_jobs.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ScanJobState {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScanJob {
+    pub library_id: String,
+    pub state: ScanJobState,
+    /// Directories (as their string path) that have been fully walked;
+    /// skipped on resume.
+    #[serde(default)]
+    pub completed_dirs: HashSet<String>,
+    /// File paths that already produced metadata (or were confirmed not to
+    /// need any); skipped on resume.
+    #[serde(default)]
+    pub scanned_files: HashSet<String>,
+    #[serde(default)]
+    pub files_scanned: u64,
+    #[serde(default)]
+    pub tasks_queued: u64,
+    /// Files a worker task resolved to a TMDB match for. Distinct from
+    /// `files_scanned`, which just means the walk looked at the file.
+    #[serde(default)]
+    pub matched_files: u64,
+    /// Files a worker task processed but couldn't match (or errored on).
+    #[serde(default)]
+    pub failed_files: u64,
+}
+
+impl ScanJob {
+    fn new(library_id: &str) -> Self {
+        Self {
+            library_id: library_id.to_string(),
+            state: ScanJobState::Queued,
+            completed_dirs: HashSet::new(),
+            scanned_files: HashSet::new(),
+            files_scanned: 0,
+            tasks_queued: 0,
+            matched_files: 0,
+            failed_files: 0,
+        }
+    }
+}
+
+/// Keyed by `Library.id`. Loaded once at startup and rewritten to
+/// `scan_jobs.json` (mirroring `auth::AuthState`/`libraries.json`'s
+/// load-whole-file/write-whole-file persistence) at job-state and
+/// directory-completion boundaries.
+#[derive(Clone)]
+pub struct ScanJobStore {
+    jobs: Arc<RwLock<HashMap<String, ScanJob>>>,
+}
+
+impl ScanJobStore {
+    pub async fn load() -> Self {
+        let jobs = match tokio::fs::read_to_string(SCAN_JOBS_FILE).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+        println!("[scan_job] Loaded {} scan job(s) from {}", jobs.len(), SCAN_JOBS_FILE);
+        Self {
+            jobs: Arc::new(RwLock::new(jobs)),
+        }
+    }
+
+    async fn save(&self) {
+        let jobs = self.jobs.read().await;
+        if let Ok(content) = serde_json::to_string_pretty(&*jobs) {
+            let _ = tokio::fs::write(SCAN_JOBS_FILE, content).await;
+        }
+    }
+
+    /// Returns the job for `library_id`, resuming whatever progress was
+    /// checkpointed, or creates a fresh `Queued` one.
+    pub async fn job_for(&self, library_id: &str) -> ScanJob {
+        let mut jobs = self.jobs.write().await;
+        jobs.entry(library_id.to_string())
+            .or_insert_with(|| ScanJob::new(library_id))
+            .clone()
+    }
+
+    pub async fn set_state(&self, library_id: &str, state: ScanJobState) {
+        {
+            let mut jobs = self.jobs.write().await;
+            jobs.entry(library_id.to_string())
+                .or_insert_with(|| ScanJob::new(library_id))
+                .state = state;
+        }
+        self.save().await;
+    }
+
+    pub async fn state_of(&self, library_id: &str) -> Option<ScanJobState> {
+        self.jobs.read().await.get(library_id).map(|j| j.state)
+    }
+
+    /// Marks `dir` as fully walked and persists -- the natural checkpoint
+    /// boundary, since a directory's entries are only ever read once.
+    pub async fn mark_dir_completed(&self, library_id: &str, dir: &str) {
+        {
+            let mut jobs = self.jobs.write().await;
+            jobs.entry(library_id.to_string())
+                .or_insert_with(|| ScanJob::new(library_id))
+                .completed_dirs
+                .insert(dir.to_string());
+        }
+        self.save().await;
+    }
+
+    pub async fn is_dir_completed(&self, library_id: &str, dir: &str) -> bool {
+        self.jobs
+            .read()
+            .await
+            .get(library_id)
+            .map(|job| job.completed_dirs.contains(dir))
+            .unwrap_or(false)
+    }
+
+    /// Records a file as already scanned. Kept in memory only -- flushed to
+    /// disk the next time `mark_dir_completed`/`set_state` saves, so a
+    /// large directory doesn't rewrite the whole checkpoint file per entry.
+    pub async fn mark_file_scanned(&self, library_id: &str, file: &str) {
+        let mut jobs = self.jobs.write().await;
+        let job = jobs
+            .entry(library_id.to_string())
+            .or_insert_with(|| ScanJob::new(library_id));
+        if job.scanned_files.insert(file.to_string()) {
+            job.files_scanned += 1;
+        }
+    }
+
+    pub async fn is_file_scanned(&self, library_id: &str, file: &str) -> bool {
+        self.jobs
+            .read()
+            .await
+            .get(library_id)
+            .map(|job| job.scanned_files.contains(file))
+            .unwrap_or(false)
+    }
+
+    pub async fn increment_tasks_queued(&self, library_id: &str) {
+        let mut jobs = self.jobs.write().await;
+        jobs.entry(library_id.to_string())
+            .or_insert_with(|| ScanJob::new(library_id))
+            .tasks_queued += 1;
+    }
+
+    /// Kept in memory only, like `mark_file_scanned` -- flushed the next
+    /// time a dir-completion/state-change save runs.
+    pub async fn increment_matched(&self, library_id: &str) {
+        let mut jobs = self.jobs.write().await;
+        jobs.entry(library_id.to_string())
+            .or_insert_with(|| ScanJob::new(library_id))
+            .matched_files += 1;
+    }
+
+    pub async fn increment_failed(&self, library_id: &str) {
+        let mut jobs = self.jobs.write().await;
+        jobs.entry(library_id.to_string())
+            .or_insert_with(|| ScanJob::new(library_id))
+            .failed_files += 1;
+    }
+
+    pub async fn snapshot(&self, library_id: &str) -> Option<ScanJob> {
+        self.jobs.read().await.get(library_id).cloned()
+    }
+}
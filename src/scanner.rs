@@ -1,163 +1,378 @@
-use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tokio::sync::mpsc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{Mutex, mpsc};
 use tokio::time::{Duration, sleep};
 
+use crate::content_hash::ContentHashIndex;
+use crate::matcher::{match_filename, match_season_folder};
 use crate::metadata::{
-    download_image, fetch_tmdb_episode_metadata, fetch_tmdb_season_metadata, process_file,
-    read_local_metadata, save_local_metadata,
+    LocalMetadata, download_poster_with_blurhash, fetch_tmdb_episode_metadata,
+    fetch_tmdb_season_metadata, process_file, read_local_metadata, save_local_metadata,
 };
 use crate::models::{AppConfig, Library, LibraryType};
+use crate::scan_job::{ScanJob, ScanJobState, ScanJobStore};
 
 pub struct Scanner {
     tx: mpsc::Sender<ScanTask>,
     config: AppConfig,
+    job_store: Arc<ScanJobStore>,
+    content_hash: Arc<ContentHashIndex>,
+    counters: Arc<ScanCounters>,
+    /// One lock per library, created on first use. `scan_library` bails out
+    /// instead of spawning a second overlapping walk when a library's lock
+    /// is already held.
+    scan_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
 }
 
+/// Running totals for the current process. Shared between the long-lived
+/// background worker (`Scanner::new`) and the one-shot worker
+/// (`Scanner::run_one_shot`), which reads them into a `ScanSummary` once the
+/// queue has drained.
+#[derive(Default)]
+struct ScanCounters {
+    files_seen: AtomicU64,
+    tasks_processed: AtomicU64,
+    errors: AtomicU64,
+}
+
+/// Returned by `Scanner::run_one_shot` once every queued task has drained.
+/// A non-zero `errors` count is the signal a CLI wrapper should use to exit
+/// non-zero.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ScanSummary {
+    pub files_seen: u64,
+    pub tasks_processed: u64,
+    pub errors: u64,
+}
+
+/// Visible to `crate::watcher`, which maps filesystem events onto the same
+/// task variants so live-scan and startup-scan share one worker/throttle.
 #[derive(Debug)]
-enum ScanTask {
-    Movie(PathBuf),
+pub(crate) enum ScanTask {
+    Movie {
+        path: PathBuf,
+        library_id: String,
+    },
     Season {
         path: PathBuf,
         tmdb_id: u64,
         season_num: u32,
+        library_id: String,
     },
     Episode {
         path: PathBuf,
         tmdb_id: u64,
         season_num: u32,
         episode_num: u32,
+        library_id: String,
     },
 }
 
 impl Scanner {
-    pub fn new(config: AppConfig) -> (Self, tokio::task::JoinHandle<()>) {
-        let (tx, mut rx) = mpsc::channel::<ScanTask>(100);
+    pub async fn new(config: AppConfig) -> (Self, tokio::task::JoinHandle<()>) {
+        let (tx, rx) = mpsc::channel::<ScanTask>(100);
         let worker_config = config.clone();
+        let job_store = Arc::new(ScanJobStore::load().await);
+        let content_hash = Arc::new(ContentHashIndex::load().await);
+        let counters = Arc::new(ScanCounters::default());
+
+        let worker_handle = tokio::spawn(run_worker(
+            rx,
+            worker_config,
+            job_store.clone(),
+            content_hash.clone(),
+            counters.clone(),
+        ));
+
+        (
+            Self {
+                tx,
+                config,
+                job_store,
+                content_hash,
+                counters,
+                scan_locks: Arc::new(Mutex::new(HashMap::new())),
+            },
+            worker_handle,
+        )
+    }
 
-        let worker_handle = tokio::spawn(async move {
-            println!("[scanner] Background worker started");
-            while let Some(task) = rx.recv().await {
-                // Rate limiting (Throttle)
-                sleep(Duration::from_millis(500)).await;
+    /// Starts a `Watcher` over `libraries`, feeding the same `ScanTask`
+    /// channel `self`'s worker loop already reads from -- keeps `tx` out of
+    /// `main`'s hands so the watcher and the worker always agree on which
+    /// channel is live.
+    pub fn spawn_watcher(&self, libraries: Vec<Library>) -> crate::watcher::Watcher {
+        crate::watcher::Watcher::start(libraries, self.tx.clone())
+    }
 
-                match task {
-                    ScanTask::Movie(path) => {
-                        println!("[scanner] Worker processing Movie: {:?}", path.file_name());
-                        if let Err(e) = process_file(&path, &worker_config, false).await {
-                            eprintln!("[scanner] Error processing movie {:?}: {}", path, e);
-                        }
-                    }
-                    ScanTask::Season {
-                        path,
-                        tmdb_id,
-                        season_num,
-                    } => {
-                        println!(
-                            "[scanner] Worker processing Season: S{:02} (Show={})",
-                            season_num, tmdb_id
-                        );
-                        match fetch_tmdb_season_metadata(&worker_config, tmdb_id, season_num).await
-                        {
-                            Ok(Some(meta)) => {
-                                if let Err(e) = save_local_metadata(&path, &meta).await {
-                                    eprintln!("[scanner] Failed to save season metadata: {}", e);
-                                } else {
-                                    if let Some(poster) = meta.poster_path {
-                                        let img_path = path.parent().unwrap().join(format!(
-                                            "{}.jpg",
-                                            path.file_name().unwrap().to_string_lossy()
-                                        ));
-                                        let _ = download_image(&worker_config, &poster, &img_path)
-                                            .await;
-                                    }
-                                }
-                            }
-                            Ok(None) => {
-                                println!("[scanner] No metadata found for Season {}", season_num)
-                            }
-                            Err(e) => eprintln!("[scanner] Error fetching season metadata: {}", e),
-                        }
-                    }
-                    ScanTask::Episode {
-                        path,
-                        tmdb_id,
-                        season_num,
-                        episode_num,
-                    } => {
-                        println!(
-                            "[scanner] Worker processing Episode: S{:02}E{:02} (Show={})",
-                            season_num, episode_num, tmdb_id
-                        );
-                        match fetch_tmdb_episode_metadata(
-                            &worker_config,
-                            tmdb_id,
-                            season_num,
-                            episode_num,
-                        )
-                        .await
-                        {
-                            Ok(Some(meta)) => {
-                                if let Err(e) = save_local_metadata(&path, &meta).await {
-                                    eprintln!("[scanner] Failed to save episode metadata: {}", e);
-                                } else {
-                                    if let Some(poster) = meta.poster_path {
-                                        // For episodes, image usually goes next to file too? Or no image?
-                                        // Let's download valid internal metadata. title="", poster=""
-                                        let img_path = path.parent().unwrap().join(format!(
-                                            "{}.jpg",
-                                            path.file_name().unwrap().to_string_lossy()
-                                        ));
-                                        let _ = download_image(&worker_config, &poster, &img_path)
-                                            .await;
-                                    }
-                                }
-                            }
-                            Ok(None) => println!(
-                                "[scanner] No metadata found for Episode S{:02}E{:02}",
-                                season_num, episode_num
-                            ),
-                            Err(e) => eprintln!("[scanner] Error fetching episode metadata: {}", e),
-                        }
-                    }
+    /// Walks `libraries` to completion, processes every queued task, then
+    /// returns a summary instead of running forever -- for cron/CI
+    /// "index this folder and quit" usage, as opposed to `new`'s long-lived
+    /// background worker. A CLI entry point should exit non-zero when
+    /// `summary.errors > 0`.
+    pub async fn run_one_shot(config: AppConfig, libraries: &[Library]) -> ScanSummary {
+        let (tx, rx) = mpsc::channel::<ScanTask>(100);
+        let job_store = Arc::new(ScanJobStore::load().await);
+        let content_hash = Arc::new(ContentHashIndex::load().await);
+        let counters = Arc::new(ScanCounters::default());
+
+        let worker_handle = tokio::spawn(run_worker(
+            rx,
+            config.clone(),
+            job_store.clone(),
+            content_hash.clone(),
+            counters.clone(),
+        ));
+
+        for library in libraries {
+            job_store
+                .set_state(&library.id, ScanJobState::Running)
+                .await;
+
+            match library.kind {
+                LibraryType::Movies => {
+                    Self::scan_movies(
+                        config.clone(),
+                        library.path.clone(),
+                        tx.clone(),
+                        library.id.clone(),
+                        job_store.clone(),
+                        content_hash.clone(),
+                        counters.clone(),
+                        library.hide_clutter,
+                        library.clutter_extra_patterns.clone(),
+                    )
+                    .await;
+                }
+                LibraryType::TVShows => {
+                    Self::scan_tv_shows(
+                        config.clone(),
+                        library.path.clone(),
+                        tx.clone(),
+                        library.id.clone(),
+                        job_store.clone(),
+                        counters.clone(),
+                        false,
+                        library.hide_clutter,
+                        library.clutter_extra_patterns.clone(),
+                    )
+                    .await;
+                }
+                LibraryType::Anime => {
+                    Self::scan_tv_shows(
+                        config.clone(),
+                        library.path.clone(),
+                        tx.clone(),
+                        library.id.clone(),
+                        job_store.clone(),
+                        counters.clone(),
+                        true,
+                        library.hide_clutter,
+                        library.clutter_extra_patterns.clone(),
+                    )
+                    .await;
+                }
+                _ => {
+                    println!(
+                        "[scanner] Skipping unsupported library type: {:?}",
+                        library.kind
+                    );
+                    continue;
                 }
             }
-            println!("[scanner] Background worker stopped");
-        });
 
-        (Self { tx, config }, worker_handle)
+            if job_store.state_of(&library.id).await == Some(ScanJobState::Running) {
+                job_store
+                    .set_state(&library.id, ScanJobState::Completed)
+                    .await;
+            }
+        }
+
+        // Every walk above has finished queueing, and `tx` here was the
+        // last sender still alive (each walk only ever cloned it). Dropping
+        // it closes the channel, so `run_worker`'s `rx.recv()` returns
+        // `None` once the queue drains instead of waiting forever.
+        drop(tx);
+        let _ = worker_handle.await;
+
+        ScanSummary {
+            files_seen: counters.files_seen.load(Ordering::Relaxed),
+            tasks_processed: counters.tasks_processed.load(Ordering::Relaxed),
+            errors: counters.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// A clone of the task sender, for `crate::watcher::Watcher` to feed
+    /// live filesystem events into the same worker/throttle as a full scan.
+    pub(crate) fn sender(&self) -> mpsc::Sender<ScanTask> {
+        self.tx.clone()
+    }
+
+    /// Pauses an in-progress scan; the walk checks this between directories
+    /// and blocks until resumed (or cancelled) rather than tearing down.
+    pub async fn pause_job(&self, library_id: &str) {
+        self.job_store
+            .set_state(library_id, ScanJobState::Paused)
+            .await;
+    }
+
+    pub async fn resume_job(&self, library_id: &str) {
+        self.job_store
+            .set_state(library_id, ScanJobState::Running)
+            .await;
     }
 
-    pub async fn scan_library(&self, library: &Library) {
+    /// Stops the in-progress walk. Checkpoints already recorded are left in
+    /// place, so the next `scan_library` call resumes rather than restarting.
+    pub async fn cancel_job(&self, library_id: &str) {
+        self.job_store
+            .set_state(library_id, ScanJobState::Queued)
+            .await;
+    }
+
+    pub async fn job_status(&self, library_id: &str) -> Option<ScanJob> {
+        self.job_store.snapshot(library_id).await
+    }
+
+    /// Spawns a walk of `library`, unless one is already in progress, in
+    /// which case this is a no-op. Returns `false` when a scan was already
+    /// running so a caller (e.g. an HTTP handler) can report that back
+    /// instead of silently dropping the request.
+    pub async fn scan_library(&self, library: &Library) -> bool {
+        let lock = {
+            let mut locks = self.scan_locks.lock().await;
+            locks
+                .entry(library.id.clone())
+                .or_insert_with(|| Arc::new(Mutex::new(())))
+                .clone()
+        };
+        let guard = match lock.try_lock_owned() {
+            Ok(guard) => guard,
+            Err(_) => {
+                println!(
+                    "[scanner] Scan for library {} already in progress; ignoring request",
+                    library.id
+                );
+                return false;
+            }
+        };
+
         let tx = self.tx.clone();
         let config = self.config.clone(); // Clone config for the task
         let lib_path = library.path.clone();
         let lib_kind = library.kind.clone();
         let lib_name = library.name.clone();
+        let library_id = library.id.clone();
+        let job_store = self.job_store.clone();
+        let content_hash = self.content_hash.clone();
+        let counters = self.counters.clone();
+        let hide_clutter = library.hide_clutter;
+        let clutter_extra_patterns = library.clutter_extra_patterns.clone();
 
         println!("[scanner] Scanning library: {} ({:?})", lib_name, lib_path);
 
         tokio::spawn(async move {
+            // Held for the walk's duration; released when the spawned task
+            // finishes (success, error, or pause/cancel early-return).
+            let _guard = guard;
+
+            // Resuming picks up wherever the checkpoint left off; a job
+            // that was previously marked Completed has every directory
+            // checkpointed, so this walk skips them all.
+            job_store
+                .set_state(&library_id, ScanJobState::Running)
+                .await;
+
             match lib_kind {
                 LibraryType::Movies => {
-                    Self::scan_movies(lib_path, tx).await;
+                    Self::scan_movies(
+                        config.clone(),
+                        lib_path,
+                        tx,
+                        library_id.clone(),
+                        job_store.clone(),
+                        content_hash,
+                        counters,
+                        hide_clutter,
+                        clutter_extra_patterns,
+                    )
+                    .await;
                 }
                 LibraryType::TVShows => {
-                    Self::scan_tv_shows(config, lib_path, tx).await;
+                    Self::scan_tv_shows(
+                        config,
+                        lib_path,
+                        tx,
+                        library_id.clone(),
+                        job_store.clone(),
+                        counters,
+                        false,
+                        hide_clutter,
+                        clutter_extra_patterns,
+                    )
+                    .await;
+                }
+                LibraryType::Anime => {
+                    Self::scan_tv_shows(
+                        config,
+                        lib_path,
+                        tx,
+                        library_id.clone(),
+                        job_store.clone(),
+                        counters,
+                        true,
+                        hide_clutter,
+                        clutter_extra_patterns,
+                    )
+                    .await;
                 }
                 _ => {
                     println!(
                         "[scanner] Skipping unsupported library type: {:?}",
                         lib_kind
                     );
+                    return;
                 }
             }
+
+            // Only reached if the walk ran to completion rather than
+            // returning early for a pause/cancel.
+            if job_store.state_of(&library_id).await == Some(ScanJobState::Running) {
+                job_store
+                    .set_state(&library_id, ScanJobState::Completed)
+                    .await;
+            }
         });
+
+        true
     }
 
-    async fn scan_movies(lib_path: PathBuf, tx: mpsc::Sender<ScanTask>) {
+    async fn scan_movies(
+        config: AppConfig,
+        lib_path: PathBuf,
+        tx: mpsc::Sender<ScanTask>,
+        library_id: String,
+        job_store: Arc<ScanJobStore>,
+        content_hash: Arc<ContentHashIndex>,
+        counters: Arc<ScanCounters>,
+        hide_clutter: bool,
+        clutter_extra_patterns: Vec<String>,
+    ) {
         let mut dirs = vec![lib_path];
         while let Some(dir) = dirs.pop() {
+            if !wait_while_runnable(&job_store, &library_id).await {
+                println!("[scanner] Movie scan for {} paused/cancelled", library_id);
+                return;
+            }
+
+            let dir_key = dir.to_string_lossy().to_string();
+            if job_store.is_dir_completed(&library_id, &dir_key).await {
+                continue;
+            }
+
             // Yield to allow other tasks (like HTTP requests) to run
             tokio::task::yield_now().await;
 
@@ -172,10 +387,26 @@ impl Scanner {
             while let Ok(Some(entry)) = entries.next_entry().await {
                 let path = entry.path();
                 if path.is_dir() {
+                    let dir_name = path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string();
+                    if hide_clutter
+                        && crate::matcher::is_clutter_name(&dir_name, &clutter_extra_patterns)
+                    {
+                        println!("[scanner] Skipping clutter directory: {:?}", dir_name);
+                        continue;
+                    }
                     dirs.push(path);
                     continue;
                 }
 
+                let file_key = path.to_string_lossy().to_string();
+                if job_store.is_file_scanned(&library_id, &file_key).await {
+                    continue;
+                }
+
                 // Throttle: sleep 1ms per file check to act as "low priority" background task
                 sleep(Duration::from_millis(1)).await;
 
@@ -183,28 +414,110 @@ impl Scanner {
                     let lower_ext = ext.to_lowercase();
                     match lower_ext.as_str() {
                         "mp4" | "mkv" | "avi" | "mov" | "webm" | "m4v" | "flv" | "wmv" => {
-                            if !has_metadata(&path) {
-                                println!(
-                                    "[scanner] Queueing missing metadata (Movie): {:?}",
-                                    path.file_name()
-                                );
-                                if let Err(e) = tx.send(ScanTask::Movie(path)).await {
-                                    eprintln!("[scanner] Failed to queue item: {}", e);
-                                    break;
+                            let file_name = path
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .to_string();
+                            let is_clutter = hide_clutter && {
+                                let size = tokio::fs::metadata(&path)
+                                    .await
+                                    .map(|m| m.len())
+                                    .unwrap_or(u64::MAX);
+                                crate::matcher::is_clutter(
+                                    &file_name,
+                                    size,
+                                    &clutter_extra_patterns,
+                                )
+                            };
+                            if is_clutter {
+                                println!("[scanner] Skipping clutter file: {:?}", file_name);
+                                job_store.mark_file_scanned(&library_id, &file_key).await;
+                                continue;
+                            }
+
+                            counters.files_seen.fetch_add(1, Ordering::Relaxed);
+                            if !has_metadata(&path).await {
+                                match crate::content_hash::hash_file(
+                                    &path,
+                                    config.cheap_fingerprint,
+                                )
+                                .await
+                                {
+                                    Ok(hash) => {
+                                        if let Some((existing, original_path)) =
+                                            content_hash.lookup(&hash).await
+                                        {
+                                            println!(
+                                                "[scanner] Duplicate content for {:?} (matches {:?}); reusing existing metadata",
+                                                path.file_name(),
+                                                original_path.file_name()
+                                            );
+                                            reuse_metadata(&original_path, &path, &existing).await;
+                                            content_hash.record(&hash, &path, &existing).await;
+                                        } else {
+                                            println!(
+                                                "[scanner] Queueing missing metadata (Movie): {:?}",
+                                                path.file_name()
+                                            );
+                                            content_hash.mark_queued(&path, &hash).await;
+                                            if let Err(e) = tx
+                                                .send(ScanTask::Movie {
+                                                    path: path.clone(),
+                                                    library_id: library_id.clone(),
+                                                })
+                                                .await
+                                            {
+                                                eprintln!("[scanner] Failed to queue item: {}", e);
+                                                break;
+                                            }
+                                            job_store.increment_tasks_queued(&library_id).await;
+                                            // Small sleep after queuing to prevent channel saturation bursts
+                                            sleep(Duration::from_millis(10)).await;
+                                        }
+                                    }
+                                    Err(e) => {
+                                        eprintln!(
+                                            "[scanner] Failed to hash {:?}: {}; queueing normally",
+                                            path, e
+                                        );
+                                        if let Err(e) = tx
+                                            .send(ScanTask::Movie {
+                                                path: path.clone(),
+                                                library_id: library_id.clone(),
+                                            })
+                                            .await
+                                        {
+                                            eprintln!("[scanner] Failed to queue item: {}", e);
+                                            break;
+                                        }
+                                        job_store.increment_tasks_queued(&library_id).await;
+                                        sleep(Duration::from_millis(10)).await;
+                                    }
                                 }
-                                // Small sleep after queuing to prevent channel saturation bursts
-                                sleep(Duration::from_millis(10)).await;
                             }
                         }
                         _ => {}
                     }
                 }
+                job_store.mark_file_scanned(&library_id, &file_key).await;
             }
+            job_store.mark_dir_completed(&library_id, &dir_key).await;
         }
         println!("[scanner] Finished scanning Movies library");
     }
 
-    async fn scan_tv_shows(config: AppConfig, lib_path: PathBuf, tx: mpsc::Sender<ScanTask>) {
+    async fn scan_tv_shows(
+        config: AppConfig,
+        lib_path: PathBuf,
+        tx: mpsc::Sender<ScanTask>,
+        library_id: String,
+        job_store: Arc<ScanJobStore>,
+        counters: Arc<ScanCounters>,
+        is_anime: bool,
+        hide_clutter: bool,
+        clutter_extra_patterns: Vec<String>,
+    ) {
         let mut entries = match tokio::fs::read_dir(&lib_path).await {
             Ok(e) => e,
             Err(e) => {
@@ -215,6 +528,11 @@ impl Scanner {
 
         // Recursive scan but starting with Top-Level assumptions
         while let Ok(Some(entry)) = entries.next_entry().await {
+            if !wait_while_runnable(&job_store, &library_id).await {
+                println!("[scanner] TV show scan for {} paused/cancelled", library_id);
+                return;
+            }
+
             // Yield per entry to be polite
             tokio::task::yield_now().await;
 
@@ -223,6 +541,22 @@ impl Scanner {
                 continue;
             }
 
+            let show_name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if hide_clutter && crate::matcher::is_clutter_name(&show_name, &clutter_extra_patterns)
+            {
+                println!("[scanner] Skipping clutter directory: {:?}", show_name);
+                continue;
+            }
+
+            let show_key = path.to_string_lossy().to_string();
+            if job_store.is_dir_completed(&library_id, &show_key).await {
+                continue;
+            }
+
             // This is a SHOW folder (Top Level)
             // 1. Check if Show metadata exists
             let tmdb_id;
@@ -237,7 +571,7 @@ impl Scanner {
                 );
                 // Process inline to get ID immediately
                 // We default is_tv=true
-                match process_file(&path, &config, true).await {
+                match process_file(&path, &config, true, is_anime).await {
                     Ok(Some(meta)) => {
                         tmdb_id = Some(meta.tmdb_id);
                         println!(
@@ -264,19 +598,55 @@ impl Scanner {
             };
 
             // 2. Scan Children (Seasons/Episodes)
-            Self::scan_show_children(&path, show_id, tx.clone()).await;
+            Self::scan_show_children(
+                &path,
+                show_id,
+                tx.clone(),
+                library_id.clone(),
+                job_store.clone(),
+                counters.clone(),
+                hide_clutter,
+                &clutter_extra_patterns,
+            )
+            .await;
+            job_store.mark_dir_completed(&library_id, &show_key).await;
         }
         println!("[scanner] Finished scanning TV Shows library");
     }
 
-    async fn scan_show_children(show_path: &Path, show_id: u64, tx: mpsc::Sender<ScanTask>) {
+    async fn scan_show_children(
+        show_path: &Path,
+        show_id: u64,
+        tx: mpsc::Sender<ScanTask>,
+        library_id: String,
+        job_store: Arc<ScanJobStore>,
+        counters: Arc<ScanCounters>,
+        hide_clutter: bool,
+        clutter_extra_patterns: &[String],
+    ) {
         println!(
             "[scanner] Entering scan_show_children for {:?} (ID: {})",
             show_path, show_id
         );
-        let mut dirs = vec![show_path.to_path_buf()];
+        // Each stack entry carries the season number inherited from its
+        // parent season folder, if any, so episode files named without a
+        // season (e.g. `Ep02`) can still be resolved against it.
+        let mut dirs: Vec<(PathBuf, Option<u32>)> = vec![(show_path.to_path_buf(), None)];
+
+        while let Some((dir, inherited_season)) = dirs.pop() {
+            if !wait_while_runnable(&job_store, &library_id).await {
+                println!(
+                    "[scanner] Show child scan for {} paused/cancelled",
+                    library_id
+                );
+                return;
+            }
+
+            let dir_key = dir.to_string_lossy().to_string();
+            if job_store.is_dir_completed(&library_id, &dir_key).await {
+                continue;
+            }
 
-        while let Some(dir) = dirs.pop() {
             println!("[scanner] Scanning directory: {:?}", dir);
             tokio::task::yield_now().await;
 
@@ -296,22 +666,21 @@ impl Scanner {
                 if path.is_dir() {
                     // Check if Season folder
                     let file_name = path.file_name().unwrap().to_string_lossy();
-                    let season_re = Regex::new(r"(?i)season\s*(\d+)|s(\d+)").unwrap();
-                    if let Some(caps) = season_re.captures(&file_name) {
-                        let s_num = caps
-                            .get(1)
-                            .or(caps.get(2))
-                            .unwrap()
-                            .as_str()
-                            .parse::<u32>()
-                            .unwrap();
+                    if hide_clutter
+                        && crate::matcher::is_clutter_name(&file_name, clutter_extra_patterns)
+                    {
+                        println!("[scanner] Skipping clutter directory: {:?}", file_name);
+                        continue;
+                    }
+                    let season_num = match_season_folder(&file_name);
+                    if let Some(s_num) = season_num {
                         println!(
                             "[scanner] Found Season folder: {:?} (Season {})",
                             file_name, s_num
                         );
 
                         // Check metadata for Season Folder
-                        if !has_metadata(&path) {
+                        if !has_metadata(&path).await {
                             println!(
                                 "[scanner] Queueing missing metadata (Season {}): {:?}",
                                 s_num,
@@ -322,13 +691,20 @@ impl Scanner {
                                     path: path.clone(),
                                     tmdb_id: show_id,
                                     season_num: s_num,
+                                    library_id: library_id.clone(),
                                 })
                                 .await;
+                            job_store.increment_tasks_queued(&library_id).await;
                             sleep(Duration::from_millis(10)).await;
                         }
                     }
-                    // Recurse
-                    dirs.push(path);
+                    // Recurse, inheriting this folder's season if it is one
+                    dirs.push((path, season_num.or(inherited_season)));
+                    continue;
+                }
+
+                let file_key = path.to_string_lossy().to_string();
+                if job_store.is_file_scanned(&library_id, &file_key).await {
                     continue;
                 }
 
@@ -336,20 +712,29 @@ impl Scanner {
                 if let Some(ext) = path.extension().and_then(|s| s.to_str()) {
                     match ext.to_lowercase().as_str() {
                         "mp4" | "mkv" | "avi" | "mov" | "webm" | "m4v" | "flv" | "wmv" => {
-                            if !has_metadata(&path) {
-                                // Try parse SxxExx
-                                let file_name = path.file_name().unwrap().to_string_lossy();
-                                // Loose regex to catch S01E01 or 1x01
-                                let ep_re = Regex::new(r"(?i)[sS](\d{1,2})[eE](\d{1,2})").unwrap();
-
-                                if let Some(caps) = ep_re.captures(&file_name) {
-                                    let s_num = caps[1].parse::<u32>().unwrap();
-                                    let e_num = caps[2].parse::<u32>().unwrap();
+                            let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+                            let is_clutter = hide_clutter && {
+                                let size = tokio::fs::metadata(&path)
+                                    .await
+                                    .map(|m| m.len())
+                                    .unwrap_or(u64::MAX);
+                                crate::matcher::is_clutter(&file_name, size, clutter_extra_patterns)
+                            };
+                            if is_clutter {
+                                println!("[scanner] Skipping clutter file: {:?}", file_name);
+                                job_store.mark_file_scanned(&library_id, &file_key).await;
+                                continue;
+                            }
+
+                            counters.files_seen.fetch_add(1, Ordering::Relaxed);
+                            if !has_metadata(&path).await {
+                                let m = match_filename(&file_name, inherited_season, true);
+
+                                if let (Some(s_num), Some(e_num)) = (m.season, m.episode) {
                                     println!(
                                         "[scanner] Found Episode file: {:?} (S{}E{})",
                                         file_name, s_num, e_num
                                     );
-
                                     println!(
                                         "[scanner] Queueing missing metadata (Episode S{:02}E{:02}): {:?}",
                                         s_num,
@@ -362,21 +747,233 @@ impl Scanner {
                                             tmdb_id: show_id,
                                             season_num: s_num,
                                             episode_num: e_num,
+                                            library_id: library_id.clone(),
                                         })
                                         .await;
+                                    job_store.increment_tasks_queued(&library_id).await;
                                     sleep(Duration::from_millis(10)).await;
+                                } else if let Some(abs_ep) = m.episode_absolute {
+                                    // No SxxExx/NxNN pair found; treat the
+                                    // trailing number as an absolute episode
+                                    // count, defaulting to Season 1 when no
+                                    // season folder is in scope (the common
+                                    // layout for single-season/anime shows).
+                                    let s_num = inherited_season.unwrap_or(1);
+                                    println!(
+                                        "[scanner] Found absolute-numbered Episode file: {:?} (abs {}, treated as S{:02}E{:02})",
+                                        file_name, abs_ep, s_num, abs_ep
+                                    );
+                                    let _ = tx
+                                        .send(ScanTask::Episode {
+                                            path: path.clone(),
+                                            tmdb_id: show_id,
+                                            season_num: s_num,
+                                            episode_num: abs_ep,
+                                            library_id: library_id.clone(),
+                                        })
+                                        .await;
+                                    job_store.increment_tasks_queued(&library_id).await;
+                                    sleep(Duration::from_millis(10)).await;
+                                } else {
+                                    println!(
+                                        "[scanner] Could not determine season/episode for {:?}; skipping",
+                                        file_name
+                                    );
                                 }
                             }
                         }
                         _ => {}
                     }
                 }
+                job_store.mark_file_scanned(&library_id, &file_key).await;
             }
+            job_store.mark_dir_completed(&library_id, &dir_key).await;
         }
     }
 }
 
-fn has_metadata(video_path: &Path) -> bool {
+/// Consumes queued `ScanTask`s until the channel closes (all senders
+/// dropped) and the queue is drained, updating `counters` as it goes.
+/// Shared by `Scanner::new`'s long-lived background worker and
+/// `Scanner::run_one_shot`'s drain-and-exit worker.
+async fn run_worker(
+    mut rx: mpsc::Receiver<ScanTask>,
+    worker_config: AppConfig,
+    job_store: Arc<ScanJobStore>,
+    content_hash: Arc<ContentHashIndex>,
+    counters: Arc<ScanCounters>,
+) {
+    println!("[scanner] Background worker started");
+    while let Some(task) = rx.recv().await {
+        // Rate limiting (Throttle)
+        sleep(Duration::from_millis(500)).await;
+        counters.tasks_processed.fetch_add(1, Ordering::Relaxed);
+
+        match task {
+            ScanTask::Movie { path, library_id } => {
+                println!("[scanner] Worker processing Movie: {:?}", path.file_name());
+                match process_file(&path, &worker_config, false, false).await {
+                    Ok(Some(meta)) => {
+                        crate::metadata::enrich_technical_metadata(&path, &worker_config).await;
+                        if let Some(hash) = content_hash.take_queued_hash(&path).await {
+                            content_hash.record(&hash, &path, &meta).await;
+                        }
+                        job_store.increment_matched(&library_id).await;
+                    }
+                    Ok(None) => {
+                        job_store.increment_failed(&library_id).await;
+                    }
+                    Err(e) => {
+                        counters.errors.fetch_add(1, Ordering::Relaxed);
+                        eprintln!("[scanner] Error processing movie {:?}: {}", path, e);
+                        job_store.increment_failed(&library_id).await;
+                    }
+                }
+            }
+            ScanTask::Season {
+                path,
+                tmdb_id,
+                season_num,
+                library_id,
+            } => {
+                println!(
+                    "[scanner] Worker processing Season: S{:02} (Show={})",
+                    season_num, tmdb_id
+                );
+                match fetch_tmdb_season_metadata(&worker_config, tmdb_id, season_num).await {
+                    Ok(Some(mut meta)) => {
+                        if let Some(poster) = meta.poster_path.clone() {
+                            let img_path = path.parent().unwrap().join(format!(
+                                "{}.jpg",
+                                path.file_name().unwrap().to_string_lossy()
+                            ));
+                            meta.blurhash =
+                                download_poster_with_blurhash(&worker_config, &poster, &img_path)
+                                    .await;
+                        }
+                        if let Err(e) = save_local_metadata(&path, &meta).await {
+                            counters.errors.fetch_add(1, Ordering::Relaxed);
+                            eprintln!("[scanner] Failed to save season metadata: {}", e);
+                            job_store.increment_failed(&library_id).await;
+                        } else {
+                            job_store.increment_matched(&library_id).await;
+                        }
+                    }
+                    Ok(None) => {
+                        println!("[scanner] No metadata found for Season {}", season_num);
+                        job_store.increment_failed(&library_id).await;
+                    }
+                    Err(e) => {
+                        counters.errors.fetch_add(1, Ordering::Relaxed);
+                        eprintln!("[scanner] Error fetching season metadata: {}", e);
+                        job_store.increment_failed(&library_id).await;
+                    }
+                }
+            }
+            ScanTask::Episode {
+                path,
+                tmdb_id,
+                season_num,
+                episode_num,
+                library_id,
+            } => {
+                println!(
+                    "[scanner] Worker processing Episode: S{:02}E{:02} (Show={})",
+                    season_num, episode_num, tmdb_id
+                );
+                match fetch_tmdb_episode_metadata(&worker_config, tmdb_id, season_num, episode_num)
+                    .await
+                {
+                    Ok(Some(mut meta)) => {
+                        if let Some(poster) = meta.poster_path.clone() {
+                            let img_path = path.parent().unwrap().join(format!(
+                                "{}.jpg",
+                                path.file_name().unwrap().to_string_lossy()
+                            ));
+                            meta.blurhash =
+                                download_poster_with_blurhash(&worker_config, &poster, &img_path)
+                                    .await;
+                        }
+                        if let Err(e) = save_local_metadata(&path, &meta).await {
+                            counters.errors.fetch_add(1, Ordering::Relaxed);
+                            eprintln!("[scanner] Failed to save episode metadata: {}", e);
+                            job_store.increment_failed(&library_id).await;
+                        } else {
+                            crate::metadata::enrich_technical_metadata(&path, &worker_config).await;
+                            job_store.increment_matched(&library_id).await;
+                        }
+                    }
+                    Ok(None) => {
+                        println!(
+                            "[scanner] No metadata found for Episode S{:02}E{:02}",
+                            season_num, episode_num
+                        );
+                        job_store.increment_failed(&library_id).await;
+                    }
+                    Err(e) => {
+                        counters.errors.fetch_add(1, Ordering::Relaxed);
+                        eprintln!("[scanner] Error fetching episode metadata: {}", e);
+                        job_store.increment_failed(&library_id).await;
+                    }
+                }
+            }
+        }
+    }
+    println!("[scanner] Background worker stopped");
+}
+
+/// Blocks while the job is `Paused`, polling once a second. Returns `false`
+/// if the scan should stop entirely (cancelled back to `Queued`, or no job
+/// state at all), `true` once it's clear to keep walking.
+async fn wait_while_runnable(job_store: &ScanJobStore, library_id: &str) -> bool {
+    loop {
+        match job_store.state_of(library_id).await {
+            Some(ScanJobState::Running) => return true,
+            Some(ScanJobState::Paused) => sleep(Duration::from_secs(1)).await,
+            _ => return false,
+        }
+    }
+}
+
+/// Copies `original_path`'s metadata (and poster, if any) to `new_path`
+/// instead of re-fetching from TMDB, since the content hash already matched
+/// an existing entry.
+async fn reuse_metadata(original_path: &Path, new_path: &Path, metadata: &LocalMetadata) {
+    if let Err(e) = save_local_metadata(new_path, metadata).await {
+        eprintln!(
+            "[scanner] Failed to save reused metadata for {:?}: {}",
+            new_path, e
+        );
+        return;
+    }
+
+    let original_jpg = original_path.parent().and_then(|parent| {
+        original_path
+            .file_name()
+            .map(|name| parent.join(format!("{}.jpg", name.to_string_lossy())))
+    });
+    let new_jpg = new_path.parent().and_then(|parent| {
+        new_path
+            .file_name()
+            .map(|name| parent.join(format!("{}.jpg", name.to_string_lossy())))
+    });
+
+    if let (Some(src), Some(dst)) = (original_jpg, new_jpg)
+        && src.exists()
+        && let Err(e) = tokio::fs::copy(&src, &dst).await
+    {
+        eprintln!("[scanner] Failed to copy poster for {:?}: {}", new_path, e);
+    }
+}
+
+/// Also used by `crate::watcher` to decide whether a newly-seen file still
+/// needs a scan task queued for it.
+/// Whether `video_path` already has a complete, fresh metadata sidecar
+/// (`{file}.json` and `{file}.jpg`, both required). Also requires the JSON
+/// sidecar to be at least as new as the video file, so a video replaced
+/// in-place (same name, newer content) is treated as unmatched and gets
+/// rescanned instead of silently keeping stale metadata.
+pub(crate) async fn has_metadata(video_path: &Path) -> bool {
     let file_name = match video_path.file_name() {
         Some(n) => n.to_string_lossy(),
         None => return false,
@@ -395,5 +992,18 @@ fn has_metadata(video_path: &Path) -> bool {
     // Logic said "missing metadata (json AND jpg)".
     // `read_local_metadata` needs JSON.
     // Let's stick to existing logic:
-    json_path.exists() && jpg_path.exists()
+    if !(json_path.exists() && jpg_path.exists()) {
+        return false;
+    }
+
+    let (Ok(json_meta), Ok(video_meta)) = (
+        tokio::fs::metadata(&json_path).await,
+        tokio::fs::metadata(video_path).await,
+    ) else {
+        return true;
+    };
+    match (json_meta.modified(), video_meta.modified()) {
+        (Ok(json_mtime), Ok(video_mtime)) => json_mtime >= video_mtime,
+        _ => true,
+    }
 }
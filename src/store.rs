@@ -0,0 +1,262 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::stream::{BoxStream, StreamExt};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+#[derive(Debug, Clone)]
+pub struct StoreEntry {
+    pub name: String,
+    pub key: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StoreMetadata {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+pub type ByteStream = BoxStream<'static, Result<Bytes>>;
+
+/// Abstracts reading a library's media off whatever backend it actually
+/// lives on (local disk, S3-compatible object storage, ...) so handlers
+/// never touch `std::fs`/`tokio::fs` directly. Keys are '/'-separated paths
+/// relative to the library root, the same shape used in the JSON API today.
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    /// Lists the direct children of `prefix`.
+    async fn list(&self, prefix: &str) -> Result<Vec<StoreEntry>>;
+
+    /// Stats a single key.
+    async fn metadata(&self, key: &str) -> Result<StoreMetadata>;
+
+    /// Opens `key`, optionally restricted to a byte range, as a stream of chunks.
+    async fn open_range(&self, key: &str, range: Option<Range<u64>>) -> Result<ByteStream>;
+}
+
+// --- Local filesystem ---
+
+pub struct LocalFsStore {
+    root: PathBuf,
+}
+
+impl LocalFsStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn resolve(&self, key: &str) -> PathBuf {
+        self.root.join(key.trim_start_matches('/'))
+    }
+}
+
+#[async_trait]
+impl MediaStore for LocalFsStore {
+    async fn list(&self, prefix: &str) -> Result<Vec<StoreEntry>> {
+        let dir = self.resolve(prefix);
+        let mut read_dir = tokio::fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("Failed to read dir: {:?}", dir))?;
+
+        let mut entries = Vec::new();
+        while let Some(entry) = read_dir.next_entry().await? {
+            let meta = entry.metadata().await?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let full_path = entry.path();
+            let key = full_path
+                .strip_prefix(&self.root)
+                .unwrap_or(&full_path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            entries.push(StoreEntry {
+                name,
+                key,
+                is_dir: meta.is_dir(),
+                size: meta.len(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    async fn metadata(&self, key: &str) -> Result<StoreMetadata> {
+        let meta = tokio::fs::metadata(self.resolve(key))
+            .await
+            .with_context(|| format!("Failed to stat key: {}", key))?;
+        Ok(StoreMetadata {
+            size: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+
+    async fn open_range(&self, key: &str, range: Option<Range<u64>>) -> Result<ByteStream> {
+        let mut file = tokio::fs::File::open(self.resolve(key))
+            .await
+            .with_context(|| format!("Failed to open key: {}", key))?;
+
+        let stream = match range {
+            Some(r) => {
+                file.seek(std::io::SeekFrom::Start(r.start)).await?;
+                ReaderStream::new(file.take(r.end - r.start))
+                    .map(|chunk| chunk.map_err(anyhow::Error::from))
+                    .boxed()
+            }
+            None => ReaderStream::new(file)
+                .map(|chunk| chunk.map_err(anyhow::Error::from))
+                .boxed(),
+        };
+
+        Ok(stream)
+    }
+}
+
+// --- S3-compatible object storage ---
+
+pub struct S3Store {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    pub async fn new(
+        bucket: String,
+        region: String,
+        prefix: String,
+        access_key_id: String,
+        secret_access_key: String,
+        endpoint: Option<String>,
+    ) -> Self {
+        let credentials = aws_sdk_s3::config::Credentials::new(
+            access_key_id,
+            secret_access_key,
+            None,
+            None,
+            "sratim-library",
+        );
+
+        let mut config = aws_sdk_s3::config::Builder::new()
+            .region(aws_sdk_s3::config::Region::new(region))
+            .credentials_provider(credentials)
+            .behavior_version(aws_sdk_s3::config::BehaviorVersion::latest());
+
+        if let Some(endpoint) = endpoint {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Self {
+            client: aws_sdk_s3::Client::from_conf(config.build()),
+            bucket,
+            prefix,
+        }
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        let prefix = self.prefix.trim_end_matches('/');
+        let key = key.trim_start_matches('/');
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", prefix, key)
+        }
+    }
+
+    fn strip_prefix(&self, key: &str) -> String {
+        let prefix = format!("{}/", self.prefix.trim_end_matches('/'));
+        key.strip_prefix(&prefix).unwrap_or(key).to_string()
+    }
+}
+
+#[async_trait]
+impl MediaStore for S3Store {
+    async fn list(&self, prefix: &str) -> Result<Vec<StoreEntry>> {
+        let full_prefix = self.full_key(prefix);
+        let normalized_prefix = if full_prefix.is_empty() || full_prefix.ends_with('/') {
+            full_prefix
+        } else {
+            format!("{}/", full_prefix)
+        };
+
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&normalized_prefix)
+            .delimiter("/")
+            .send()
+            .await
+            .context("S3 list_objects_v2 failed")?;
+
+        let mut entries = Vec::new();
+
+        for common_prefix in output.common_prefixes() {
+            if let Some(p) = common_prefix.prefix() {
+                let name = p.trim_end_matches('/').rsplit('/').next().unwrap_or(p);
+                entries.push(StoreEntry {
+                    name: name.to_string(),
+                    key: self.strip_prefix(p.trim_end_matches('/')),
+                    is_dir: true,
+                    size: 0,
+                });
+            }
+        }
+
+        for object in output.contents() {
+            if let Some(k) = object.key() {
+                let name = k.rsplit('/').next().unwrap_or(k);
+                entries.push(StoreEntry {
+                    name: name.to_string(),
+                    key: self.strip_prefix(k),
+                    is_dir: false,
+                    size: object.size().unwrap_or(0) as u64,
+                });
+            }
+        }
+
+        Ok(entries)
+    }
+
+    async fn metadata(&self, key: &str) -> Result<StoreMetadata> {
+        let output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(self.full_key(key))
+            .send()
+            .await
+            .context("S3 head_object failed")?;
+
+        Ok(StoreMetadata {
+            size: output.content_length().unwrap_or(0) as u64,
+            modified: output
+                .last_modified()
+                .and_then(|t| SystemTime::try_from(t.to_owned()).ok()),
+        })
+    }
+
+    async fn open_range(&self, key: &str, range: Option<Range<u64>>) -> Result<ByteStream> {
+        let mut request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.full_key(key));
+
+        if let Some(r) = range {
+            request = request.range(format!("bytes={}-{}", r.start, r.end.saturating_sub(1)));
+        }
+
+        let output = request.send().await.context("S3 get_object failed")?;
+
+        Ok(output
+            .body
+            .map(|chunk| chunk.map_err(anyhow::Error::from))
+            .boxed())
+    }
+}
@@ -0,0 +1,5 @@
+//! Segmented-streaming support for `routes::video`'s HLS endpoints.
+
+pub mod manager;
+pub mod process;
+pub mod profile;
@@ -0,0 +1,438 @@
+//! Session lifecycle manager for HLS-segmented transcodes, alongside the
+//! single-shot `spawn_ffmpeg`/`extract_subtitle` children in
+//! `streaming::process`. Those are killed the moment their response stream
+//! drops, so their lifetime needs no tracking once spawned. An HLS session is
+//! different: ffmpeg keeps writing segments in the background while the
+//! client polls the growing playlist and fetches segments one request at a
+//! time, so something has to remember it exists, know when to stop feeding
+//! it, and clean up after a client that simply stops asking.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::Mutex as StdMutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use axum::body::Bytes;
+use serde::Serialize;
+use tokio::process::Child;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use super::process::{KeyframeCache, find_keyframe};
+
+/// Identifies one task the manager is responsible for. `Stream`/`Subtitles`
+/// name the one-shot `spawn_ffmpeg`/`extract_subtitle` children, which
+/// `routes::video::stream_video`/`get_subtitles` register via
+/// `register_progress` for progress reporting and stall detection; their
+/// process otherwise lives and dies with the response stream. `Hls` is this
+/// session kind: a long-lived segmented transcode with no single response
+/// stream to tie its lifetime to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskKey {
+    Stream(Uuid),
+    Subtitles(Uuid),
+    Hls(Uuid),
+}
+
+/// Target length of one HLS segment. ffmpeg's `-hls_time` only suggests a
+/// boundary -- it still cuts on the next keyframe -- so actual segments are
+/// close to, not exactly, this length.
+pub const HLS_SEGMENT_SECONDS: f64 = 6.0;
+
+/// How many segments the encoder is allowed to finish beyond the last one a
+/// client actually fetched before the reaper decides it's racing ahead for
+/// no one and kills it.
+const MAX_SEGMENTS_AHEAD: u64 = 15;
+/// How long a session can go without a playlist/segment request before the
+/// reaper treats it as abandoned.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const REAPER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// One live HLS session. `encoder_segment` is updated by a background task
+/// parsing the ffmpeg child's `-progress pipe:1` output;
+/// `last_requested_segment`/`last_access` are updated by
+/// `TranscodeManager::touch` whenever the playlist or a segment is fetched.
+pub struct HlsSession {
+    child: Mutex<Child>,
+    pub dir: PathBuf,
+    encoder_segment: AtomicU64,
+    last_requested_segment: AtomicU64,
+    last_access: StdMutex<Instant>,
+}
+
+/// One parsed snapshot of ffmpeg's `-progress pipe:2` output for a single
+/// `TaskKey`. Fields are `None` until that key has appeared at least once --
+/// not every report line carries every key.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProgressStats {
+    pub frame: Option<u64>,
+    pub fps: Option<f64>,
+    pub out_time_secs: Option<f64>,
+    pub speed: Option<f64>,
+    pub bitrate: Option<String>,
+}
+
+/// `ProgressStats` plus the bookkeeping `TranscodeManager` needs to detect a
+/// stalled encoder: the pid to kill and the last time any line was parsed
+/// for it.
+struct ProgressEntry {
+    stats: ProgressStats,
+    updated_at: Instant,
+    pid: u32,
+}
+
+/// How long a registered task (`TaskKey::Stream`/`TaskKey::Subtitles`) can
+/// go without a single progress line before it's considered stalled and
+/// killed outright. HLS sessions are not registered for progress; only the
+/// idle/raced-ahead checks in `spawn_reaper` apply to them.
+const PROGRESS_STALL_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Tracks every live HLS session, keyed by the `Uuid` minted in
+/// `start_hls` and handed back to the client as the session id. Reaped by
+/// `spawn_reaper`. Also tracks live ffmpeg progress for any `TaskKey`
+/// (including the one-shot `Stream`/`Subtitles` tasks `spawn_ffmpeg`/
+/// `extract_subtitle` back) so callers can report current position/speed
+/// and so stalled encoders get killed proactively.
+pub struct TranscodeManager {
+    sessions: Mutex<HashMap<Uuid, Arc<HlsSession>>>,
+    progress: Mutex<HashMap<TaskKey, ProgressEntry>>,
+    keyframes: KeyframeCache,
+}
+
+impl TranscodeManager {
+    pub fn new() -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            keyframes: KeyframeCache::new(),
+        }
+    }
+
+    /// Nearest keyframe at or before `target`, backed by a per-file index
+    /// cached both in memory and on disk (see `streaming::process::KeyframeCache`)
+    /// instead of a fresh `ffprobe` scan per call.
+    pub async fn find_keyframe(&self, path: &Path, target: f64) -> Result<f64> {
+        find_keyframe(&self.keyframes, path, target).await
+    }
+
+    /// JPEG poster frame at `timestamp`, keyframe-aligned via the same
+    /// cache `find_keyframe` uses.
+    pub async fn extract_thumbnail(&self, path: &Path, timestamp: f64) -> Result<Bytes> {
+        super::process::extract_thumbnail(path, &self.keyframes, timestamp).await
+    }
+
+    /// Starts tracking a freshly spawned child under `key`, so its idle
+    /// timer runs from the moment it's spawned rather than its first parsed
+    /// progress line.
+    pub async fn register_progress(&self, key: TaskKey, pid: u32) {
+        self.progress.lock().await.insert(
+            key,
+            ProgressEntry {
+                stats: ProgressStats::default(),
+                updated_at: Instant::now(),
+                pid,
+            },
+        );
+    }
+
+    /// Parses one line of ffmpeg's `-progress pipe:2` output and folds it
+    /// into `key`'s current snapshot. A full report is several lines (one
+    /// per key) ending in `progress=continue`/`progress=end`; callers feed
+    /// lines in as they arrive rather than waiting for the terminator, so
+    /// the snapshot is always at least as fresh as the last line read.
+    pub async fn handle_progress_line(&self, key: TaskKey, line: &str) {
+        let mut progress = self.progress.lock().await;
+        let Some(entry) = progress.get_mut(&key) else {
+            return;
+        };
+        entry.updated_at = Instant::now();
+
+        if let Some((k, v)) = line.trim().split_once('=') {
+            match k {
+                "frame" => entry.stats.frame = v.parse().ok(),
+                "fps" => entry.stats.fps = v.parse().ok(),
+                "out_time_ms" => {
+                    entry.stats.out_time_secs =
+                        v.parse::<i64>().ok().map(|us| us as f64 / 1_000_000.0);
+                }
+                "speed" => entry.stats.speed = v.trim_end_matches('x').parse().ok(),
+                "bitrate" => entry.stats.bitrate = Some(v.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Stops tracking `key` -- called once its stderr reader hits EOF, so a
+    /// task that finished normally doesn't later get flagged as stalled.
+    pub async fn remove_progress(&self, key: TaskKey) {
+        self.progress.lock().await.remove(&key);
+    }
+
+    /// Current progress snapshot for `key`, if it's being tracked.
+    pub async fn progress_stats(&self, key: TaskKey) -> Option<ProgressStats> {
+        self.progress
+            .lock()
+            .await
+            .get(&key)
+            .map(|e| e.stats.clone())
+    }
+
+    /// Starts a fresh HLS session for `path`, writing a growing playlist
+    /// plus fmp4 init/segment files into their own subdirectory of
+    /// `temp_root`. `start` is snapped to the nearest keyframe via
+    /// `find_keyframe` first, so the first segment boundary lines up with a
+    /// real cut point instead of ffmpeg inserting a partial leading GOP.
+    pub async fn start_hls(
+        &self,
+        path: &Path,
+        start: f64,
+        audio_track_idx: Option<usize>,
+        video_codec: &str,
+        temp_root: &Path,
+    ) -> Result<Uuid> {
+        let aligned_start = if start > 0.0 {
+            self.find_keyframe(path, start).await.unwrap_or(start)
+        } else {
+            0.0
+        };
+
+        let session_id = Uuid::new_v4();
+        let dir = temp_root.join(session_id.to_string());
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .context("Failed to create HLS session dir")?;
+
+        let playlist_path = dir.join("playlist.m3u8");
+        let segment_pattern = dir.join("segment%05d.m4s");
+
+        let mut args = vec![
+            "-ss".to_string(),
+            aligned_start.to_string(),
+            "-i".to_string(),
+            path.to_string_lossy().to_string(),
+            "-map".to_string(),
+            "0:v:0".to_string(),
+            "-c:v".to_string(),
+            "copy".to_string(),
+        ];
+
+        if video_codec == "hevc" {
+            args.push("-tag:v".to_string());
+            args.push("hvc1".to_string());
+        }
+
+        if let Some(track_idx) = audio_track_idx {
+            args.extend_from_slice(&[
+                "-map".to_string(),
+                format!("0:a:{}", track_idx),
+                "-c:a".to_string(),
+                "aac".to_string(),
+                "-ac".to_string(),
+                "2".to_string(),
+            ]);
+        }
+
+        args.extend_from_slice(&[
+            "-f".to_string(),
+            "hls".to_string(),
+            "-hls_time".to_string(),
+            HLS_SEGMENT_SECONDS.to_string(),
+            "-hls_segment_type".to_string(),
+            "fmp4".to_string(),
+            "-hls_fmp4_init_filename".to_string(),
+            "init.mp4".to_string(),
+            "-hls_flags".to_string(),
+            "independent_segments".to_string(),
+            "-hls_list_size".to_string(),
+            "0".to_string(),
+            "-hls_segment_filename".to_string(),
+            segment_pattern.to_string_lossy().to_string(),
+            "-progress".to_string(),
+            "pipe:1".to_string(),
+            playlist_path.to_string_lossy().to_string(),
+        ]);
+
+        println!(
+            "[hls] Spawning ffmpeg for session {}: {:?}",
+            session_id, args
+        );
+
+        let mut command = tokio::process::Command::new("ffmpeg");
+        command
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = command.spawn().context("Failed to spawn ffmpeg for HLS")?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+
+        let session = Arc::new(HlsSession {
+            child: Mutex::new(child),
+            dir,
+            encoder_segment: AtomicU64::new(0),
+            last_requested_segment: AtomicU64::new(0),
+            last_access: StdMutex::new(Instant::now()),
+        });
+
+        if let Some(stdout) = stdout {
+            let session = session.clone();
+            tokio::spawn(async move {
+                use tokio::io::{AsyncBufReadExt, BufReader};
+                let mut reader = BufReader::new(stdout);
+                let mut line = String::new();
+                while let Ok(n) = reader.read_line(&mut line).await {
+                    if n == 0 {
+                        break;
+                    }
+                    // ffmpeg's `-progress` output is key=value lines;
+                    // `out_time_ms` is (despite the name) microseconds of
+                    // output produced so far.
+                    if let Some(value) = line.trim().strip_prefix("out_time_ms=")
+                        && let Ok(out_time_us) = value.parse::<i64>()
+                    {
+                        let segment =
+                            (out_time_us as f64 / 1_000_000.0 / HLS_SEGMENT_SECONDS) as u64;
+                        session.encoder_segment.store(segment, Ordering::SeqCst);
+                    }
+                    line.clear();
+                }
+            });
+        }
+
+        if let Some(stderr) = stderr {
+            tokio::spawn(async move {
+                use tokio::io::{AsyncBufReadExt, BufReader};
+                let mut reader = BufReader::new(stderr);
+                let mut line = String::new();
+                while let Ok(n) = reader.read_line(&mut line).await {
+                    if n == 0 {
+                        break;
+                    }
+                    eprint!("[ffmpeg-hls] {}", line);
+                    line.clear();
+                }
+            });
+        }
+
+        self.sessions.lock().await.insert(session_id, session);
+        Ok(session_id)
+    }
+
+    /// Records that the client just asked for `segment` (0 for the
+    /// playlist/init file), resetting the session's idle timeout and
+    /// letting the reaper compare how far ahead the encoder has run.
+    pub async fn touch(&self, id: Uuid, segment: u64) {
+        if let Some(session) = self.sessions.lock().await.get(&id) {
+            session
+                .last_requested_segment
+                .fetch_max(segment, Ordering::SeqCst);
+            *session.last_access.lock().unwrap() = Instant::now();
+        }
+    }
+
+    pub async fn playlist_path(&self, id: Uuid) -> Option<PathBuf> {
+        self.sessions
+            .lock()
+            .await
+            .get(&id)
+            .map(|s| s.dir.join("playlist.m3u8"))
+    }
+
+    /// Resolves `name` (an `init.mp4` or `segmentNNNNN.m4s` ffmpeg wrote)
+    /// within the session's own directory. Callers are responsible for
+    /// rejecting names that aren't a bare filename before calling this.
+    pub async fn segment_path(&self, id: Uuid, name: &str) -> Option<PathBuf> {
+        self.sessions
+            .lock()
+            .await
+            .get(&id)
+            .map(|s| s.dir.join(name))
+    }
+
+    /// Cancels a session early (e.g. the client explicitly stopped), killing
+    /// its ffmpeg child and purging its temp dir. Returns `true` if a
+    /// session was found.
+    pub async fn stop(&self, id: Uuid) -> bool {
+        let Some(session) = self.sessions.lock().await.remove(&id) else {
+            return false;
+        };
+        let _ = session.child.lock().await.kill().await;
+        let _ = tokio::fs::remove_dir_all(&session.dir).await;
+        true
+    }
+}
+
+impl Default for TranscodeManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Periodically kills HLS sessions that have either gone
+/// `SESSION_IDLE_TIMEOUT` without a playlist/segment request, or whose
+/// encoder has raced more than `MAX_SEGMENTS_AHEAD` segments past the last
+/// one actually fetched -- both are signs the viewer left without the
+/// encoder noticing.
+pub fn spawn_reaper(manager: Arc<TranscodeManager>) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REAPER_INTERVAL).await;
+
+            let mut sessions = manager.sessions.lock().await;
+            let mut to_remove = Vec::new();
+            for (id, session) in sessions.iter() {
+                let idle = session.last_access.lock().unwrap().elapsed() > SESSION_IDLE_TIMEOUT;
+                let encoder_segment = session.encoder_segment.load(Ordering::SeqCst);
+                let requested_segment = session.last_requested_segment.load(Ordering::SeqCst);
+                let raced_ahead = encoder_segment > requested_segment + MAX_SEGMENTS_AHEAD;
+
+                if idle || raced_ahead {
+                    println!(
+                        "[hls] Reaping session {} (idle={}, raced_ahead={})",
+                        id, idle, raced_ahead
+                    );
+                    let _ = session.child.lock().await.kill().await;
+                    let dir = session.dir.clone();
+                    tokio::spawn(async move {
+                        let _ = tokio::fs::remove_dir_all(&dir).await;
+                    });
+                    to_remove.push(*id);
+                }
+            }
+            for id in to_remove {
+                sessions.remove(&id);
+            }
+            drop(sessions);
+
+            let mut progress = manager.progress.lock().await;
+            let mut stalled = Vec::new();
+            for (key, entry) in progress.iter() {
+                if entry.updated_at.elapsed() > PROGRESS_STALL_TIMEOUT {
+                    println!(
+                        "[ffmpeg] Killing stalled task {:?} (pid {}, no progress for {:?})",
+                        key,
+                        entry.pid,
+                        entry.updated_at.elapsed()
+                    );
+                    // SAFETY: `pid` is a real process id recorded by
+                    // `register_progress` right after spawning the child
+                    // that owns it; killing it is exactly the cleanup an
+                    // abandoned ffmpeg process needs.
+                    unsafe {
+                        libc::kill(entry.pid as i32, libc::SIGKILL);
+                    }
+                    stalled.push(*key);
+                }
+            }
+            for key in stalled {
+                progress.remove(&key);
+            }
+        }
+    });
+}
@@ -1,39 +1,137 @@
-use crate::models::{AudioTrack, MovieMetadata, SubtitleTrack};
+use crate::models::{AudioTrack, ExternalSubtitle, MovieMetadata, StreamInfo, SubtitleTrack};
 use anyhow::{Context, Result};
 use axum::body::Bytes;
 use futures_core::Stream;
-use serde::Deserialize;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::process::Stdio;
+use std::sync::Arc;
 use std::task::{Context as TaskContext, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt, ReadBuf};
 use tokio::process::Child;
+use tokio::sync::Mutex;
 use tokio_util::io::ReaderStream;
 
+use super::profile::TranscodeProfile;
+
+// --- Input Source ---
+
+/// Where the bytes fed to ffmpeg/ffprobe's `-i` come from. `File` is the
+/// original, common case: a seekable path on local disk, which is what lets
+/// `spawn_ffmpeg` use `-ss` input seeking. `Stream` lets a caller hand over
+/// any `AsyncRead` instead -- e.g. media fetched from a remote/object-store
+/// backend and forwarded in-memory -- at the cost of seeking: a pipe can
+/// only be read forward once, so `-ss`/`-noaccurate_seek` don't apply, and a
+/// nonzero `start` is logged and ignored rather than silently fed a
+/// meaningless seek.
+pub enum InputSource {
+    File(PathBuf),
+    Stream(Box<dyn AsyncRead + Send + Unpin>),
+}
+
+impl InputSource {
+    fn is_stream(&self) -> bool {
+        matches!(self, InputSource::Stream(_))
+    }
+
+    /// The `-i` argument value for this input.
+    fn arg(&self) -> String {
+        match self {
+            InputSource::File(path) => path.to_string_lossy().to_string(),
+            InputSource::Stream(_) => "pipe:0".to_string(),
+        }
+    }
+}
+
+/// Spawns a background task copying `input` into `child`'s stdin, if it's a
+/// `Stream`. No-op for `File`, which ffmpeg/ffprobe read directly via `-i`.
+fn feed_stdin(input: InputSource, child: &mut Child, tag: &str) {
+    if let InputSource::Stream(mut reader) = input {
+        let mut stdin = child
+            .stdin
+            .take()
+            .expect("stdin was piped for a Stream input");
+        let tag = tag.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = tokio::io::copy(&mut reader, &mut stdin).await {
+                eprintln!("[{}] Failed to feed stdin from input stream: {}", tag, e);
+            }
+        });
+    }
+}
+
 // --- FFmpeg Spawning ---
 
 pub fn spawn_ffmpeg(
-    path: &Path,
+    input: InputSource,
     start: f64,
     audio_track_idx: Option<usize>,
     video_codec: &str,
+    profile: &TranscodeProfile,
 ) -> Result<Child> {
     let mut args = vec![
-        "-noaccurate_seek".to_string(),
-        "-ss".to_string(),
-        start.to_string(),
-        "-i".to_string(),
-        path.to_string_lossy().to_string(),
-        "-map".to_string(),
-        "0:v:0".to_string(),
-        "-c:v".to_string(),
-        "copy".to_string(), // Enforce zero transcoding
+        // Key=value progress reports on stderr (separate from the piped
+        // media data on stdout), parsed by
+        // `streaming::manager::TranscodeManager::handle_progress_line`.
+        // `-loglevel error` keeps stderr to just those reports plus real
+        // errors, instead of also interleaving ffmpeg's default per-frame
+        // human-readable status line.
+        "-progress".to_string(),
+        "pipe:2".to_string(),
+        "-loglevel".to_string(),
+        "error".to_string(),
     ];
 
-    // HEVC tagging
-    if video_codec == "hevc" {
-        args.push("-tag:v".to_string());
-        args.push("hvc1".to_string());
+    if input.is_stream() {
+        if start > 0.0 {
+            eprintln!(
+                "[stream] Ignoring start={:.2}s seek: piped stdin input isn't seekable",
+                start
+            );
+        }
+    } else {
+        args.push("-noaccurate_seek".to_string());
+        args.push("-ss".to_string());
+        args.push(start.to_string());
+    }
+
+    args.push("-i".to_string());
+    args.push(input.arg());
+    args.push("-map".to_string());
+    args.push("0:v:0".to_string());
+    args.push("-c:v".to_string());
+
+    if profile.copy {
+        args.push("copy".to_string()); // Enforce zero transcoding
+
+        // HEVC tagging
+        if video_codec == "hevc" {
+            args.push("-tag:v".to_string());
+            args.push("hvc1".to_string());
+        }
+    } else {
+        args.push(profile.encoder_name().to_string());
+
+        if let Some(crf) = profile.crf {
+            args.push("-crf".to_string());
+            args.push(crf.to_string());
+        }
+        if let Some(preset) = profile.preset {
+            args.push("-preset".to_string());
+            args.push(preset.to_string());
+        }
+        if let Some(max_bitrate_kbps) = profile.max_bitrate_kbps {
+            args.push("-maxrate".to_string());
+            args.push(format!("{}k", max_bitrate_kbps));
+            args.push("-bufsize".to_string());
+            args.push(format!("{}k", max_bitrate_kbps * 2));
+        }
+        if let Some(height) = profile.scale_height {
+            args.push("-vf".to_string());
+            args.push(format!("scale=-2:'min({},ih)'", height));
+        }
     }
 
     if let Some(track_idx) = audio_track_idx {
@@ -44,10 +142,28 @@ pub fn spawn_ffmpeg(
             "aac".to_string(),
             "-ac".to_string(),
             "2".to_string(),
+            // The AAC encoder buffers ~1024 samples of priming (encoder
+            // delay) ahead of the first real frame. Left alone, that delay
+            // shifts the re-encoded audio later than the copied video
+            // keyframe it's meant to start alongside, and the drift grows
+            // the further into the file `start` seeks. `first_pts=0`
+            // re-stamps the resampled audio to presentation time zero,
+            // trimming the priming samples instead of letting them play as
+            // extra silence/offset; `async=1` keeps it locked to that
+            // timeline rather than drifting again over a long stream.
+            "-af".to_string(),
+            "aresample=async=1:first_pts=0".to_string(),
         ]);
     }
 
     args.extend_from_slice(&[
+        // Re-stamps both streams' timestamps to start at zero relative to
+        // `start`, so the first copied video keyframe and the first
+        // `first_pts=0`-corrected audio sample line up instead of the
+        // container carrying forward the original seek offset on one
+        // stream but not the other.
+        "-avoid_negative_ts".to_string(),
+        "make_zero".to_string(),
         "-movflags".to_string(),
         "frag_keyframe+empty_moov+default_base_moof".to_string(),
         "-f".to_string(),
@@ -57,48 +173,175 @@ pub fn spawn_ffmpeg(
 
     println!("[stream] Spawning ffmpeg: {:?}", args);
 
+    let is_stream = input.is_stream();
     let mut command = tokio::process::Command::new("ffmpeg");
     command
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .kill_on_drop(true); // Safety: kill if client disconnects
+    if is_stream {
+        command.stdin(Stdio::piped());
+    }
 
-    let child = command.spawn().context("Failed to spawn ffmpeg")?;
+    let mut child = command.spawn().context("Failed to spawn ffmpeg")?;
+    feed_stdin(input, &mut child, "stream");
     Ok(child)
 }
 
-pub fn extract_subtitle(path: &Path, subtitle_track_idx: usize) -> Result<Child> {
-    let path_str = path.to_string_lossy().to_string();
+pub fn extract_subtitle(input: InputSource, subtitle_track_idx: usize) -> Result<Child> {
     let map_arg = format!("0:s:{}", subtitle_track_idx);
 
     let args = vec![
-        "-i",
-        path_str.as_str(),
-        "-map",
-        map_arg.as_str(),
-        "-c:s",
-        "webvtt",
-        "-f",
-        "webvtt",
-        "pipe:1",
+        "-progress".to_string(),
+        "pipe:2".to_string(),
+        "-loglevel".to_string(),
+        "error".to_string(),
+        "-i".to_string(),
+        input.arg(),
+        "-map".to_string(),
+        map_arg,
+        "-c:s".to_string(),
+        "webvtt".to_string(),
+        "-f".to_string(),
+        "webvtt".to_string(),
+        "pipe:1".to_string(),
     ];
 
     println!("[subtitle] Spawning ffmpeg: {:?}", args);
 
+    let is_stream = input.is_stream();
     let mut command = tokio::process::Command::new("ffmpeg");
     command
         .args(&args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped()) // Capture stderr to avoid polluting server logs, but maybe we don't need to read it
         .kill_on_drop(true);
+    if is_stream {
+        command.stdin(Stdio::piped());
+    }
 
-    let child = command
+    let mut child = command
         .spawn()
         .context("Failed to spawn ffmpeg for subtitles")?;
+    feed_stdin(input, &mut child, "subtitle");
     Ok(child)
 }
 
+/// Converts an external sidecar subtitle file to WebVTT, the same way
+/// `extract_subtitle` pulls an embedded stream. Runs every format (including
+/// already-WebVTT sidecars) through ffmpeg rather than special-casing a
+/// passthrough, so callers have one code path regardless of input format.
+pub fn extract_external_subtitle(path: &Path) -> Result<Child> {
+    let path_str = path.to_string_lossy().to_string();
+
+    let args = vec!["-i", path_str.as_str(), "-f", "webvtt", "pipe:1"];
+
+    println!("[subtitle] Spawning ffmpeg (external): {:?}", args);
+
+    let mut command = tokio::process::Command::new("ffmpeg");
+    command
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    command
+        .spawn()
+        .context("Failed to spawn ffmpeg for external subtitle")
+}
+
+/// Extracts a single JPEG poster frame at `timestamp`, snapped to the
+/// nearest keyframe via `cache` the same way `routes::video::stream_video`
+/// aligns a stream's start before calling `spawn_ffmpeg` -- so a scrubbing
+/// preview lands on a frame ffmpeg can
+/// grab directly instead of decoding through a GOP to reach it. Runs to
+/// completion and returns the frame in memory rather than a `Child`, since
+/// one JPEG is small and there's no response stream to tie a process
+/// lifetime to.
+pub async fn extract_thumbnail(
+    path: &Path,
+    cache: &KeyframeCache,
+    timestamp: f64,
+) -> Result<Bytes> {
+    let aligned = find_keyframe(cache, path, timestamp)
+        .await
+        .unwrap_or(timestamp);
+
+    let output = tokio::process::Command::new("ffmpeg")
+        .args(&[
+            "-loglevel",
+            "error",
+            "-ss",
+            &aligned.to_string(),
+            "-i",
+            &path.to_string_lossy(),
+            "-frames:v",
+            "1",
+            "-f",
+            "mjpeg",
+            "pipe:1",
+        ])
+        .output()
+        .await
+        .context("Failed to run ffmpeg for thumbnail")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ffmpeg thumbnail extraction failed"));
+    }
+
+    Ok(Bytes::from(output.stdout))
+}
+
+/// Sidecar subtitle extensions recognized next to a video file.
+const EXTERNAL_SUBTITLE_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "vtt"];
+
+/// Finds sidecar subtitle files next to `video_path` named per the filebot
+/// `{basename}.{lang}[.forced].{ext}` convention (`Movie.en.srt`,
+/// `Movie.forced.ass`, `Movie.en.forced.srt`).
+pub fn discover_external_subtitles(video_path: &Path) -> Vec<ExternalSubtitle> {
+    let (Some(parent), Some(stem)) = (video_path.parent(), video_path.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy();
+    let prefix = format!("{}.", stem);
+
+    let mut subs = Vec::new();
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return subs;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if !file_name.starts_with(&prefix) {
+            continue;
+        }
+        let Some(ext) = Path::new(&file_name).extension().and_then(|e| e.to_str()) else {
+            continue;
+        };
+        if !EXTERNAL_SUBTITLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
+            continue;
+        }
+
+        // Everything between the stem prefix and ".{ext}" is the
+        // `lang[.forced]` tag -- may be empty ("Movie.srt").
+        let tag = &file_name[prefix.len()..file_name.len() - ext.len() - 1];
+        let mut parts: Vec<&str> = tag.split('.').filter(|p| !p.is_empty()).collect();
+        let forced = parts.iter().any(|p| p.eq_ignore_ascii_case("forced"));
+        parts.retain(|p| !p.eq_ignore_ascii_case("forced"));
+        let language = parts.first().map(|s| s.to_lowercase());
+
+        subs.push(ExternalSubtitle {
+            filename: file_name,
+            language,
+            forced,
+        });
+    }
+
+    subs.sort_by(|a, b| a.filename.cmp(&b.filename));
+    subs
+}
+
 // --- Keyframe Probe ---
 
 #[derive(Deserialize)]
@@ -111,44 +354,56 @@ struct FFProbeFrameOutput {
     frames: Option<Vec<FFProbeFrame>>,
 }
 
-pub async fn find_keyframe(path: &Path, target: f64) -> Result<f64> {
-    if target <= 0.0 {
-        return Ok(0.0);
-    }
-
-    // Search window: Look back 60s. ffmpeg -ss (target-60) snaps to a keyframe before that.
-    // Then we read frames forward.
-    let search_start = (target - 60.0).max(0.0);
-
-    println!(
-        "[keyframe] Probing keyframe near {} (scanning from {})",
-        target, search_start
-    );
-
-    // Command: ffprobe -ss {search_start} -i {path} -select_streams v -skip_frame nokey -show_entries frame=pkt_pts_time -of json -read_intervals "%+70"
-    // Note: -read_intervals is relative to the seek point if we use input seeking? No, it's absolute timestamps usually.
-    // simpler: just scan 70s of duration.
+/// Sorted keyframe timestamps for one file, plus the mtime/size it was built
+/// from so a later re-encode or replace is detected instead of silently
+/// serving a stale index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KeyframeIndex {
+    mtime: u64,
+    size: u64,
+    timestamps: Vec<f64>,
+}
 
-    // We can use `-read_intervals` with `+duration` syntax relative to input?
-    // Or just let it run and pipe output? process.kill() isn't easy here.
-    // Best to use -read_intervals with absolute times if possible, or relative.
+impl KeyframeIndex {
+    fn sidecar_path(path: &Path) -> PathBuf {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".keyframes.json");
+        path.with_file_name(name)
+    }
 
-    // Let's try explicit absolute time scan range.
-    // But we need input seeking for speed.
-    // If we use input seeking `-ss`, timestamps are reset? or processed?
-    // ffprobe usually reports preserved timestamps if we don't transcode?
-    // Let's verify: `ffprobe -ss 10 -i file -show_entries frame=pkt_pts_time`.
-    // It reports timestamps relative to 0 usually? No, pkt_pts_time is usually absolute or relative to file.
+    fn matches(&self, mtime: u64, size: u64) -> bool {
+        self.mtime == mtime && self.size == size
+    }
 
-    // SAFEST: No input seeking, just read_intervals.
-    // But read_intervals failed last time.
-    // Maybe `pkt_pts_time` was missing?
+    /// Greatest keyframe timestamp `<= target + epsilon`, so an exact hit on
+    /// a keyframe still counts. `0.0` if the file starts after `target`.
+    fn lookup(&self, target: f64) -> f64 {
+        const EPSILON: f64 = 0.1;
+        match self
+            .timestamps
+            .partition_point(|&ts| ts <= target + EPSILON)
+        {
+            0 => 0.0,
+            n => self.timestamps[n - 1],
+        }
+    }
+}
 
-    // Let's use `frame=best_effort_timestamp_time,pkt_pts_time`.
+fn file_stat(path: &Path) -> Result<(u64, u64)> {
+    let meta = std::fs::metadata(path).context("Failed to stat file")?;
+    let mtime = meta
+        .modified()
+        .context("Failed to read mtime")?
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Ok((mtime, meta.len()))
+}
 
-    let start_scan = (target - 60.0).max(0.0);
-    let end_scan = target + 5.0; // slightly past target
-    let interval = format!("{}%{}", start_scan, end_scan);
+/// Runs one full-file `ffprobe` pass collecting every keyframe's timestamp,
+/// replacing the old 60-70s lookback scan a seek used to pay for every time.
+async fn build_keyframe_index(path: &Path, mtime: u64, size: u64) -> Result<KeyframeIndex> {
+    println!("[keyframe] Building keyframe index for {}", path.display());
 
     let output = tokio::process::Command::new("ffprobe")
         .args(&[
@@ -162,8 +417,6 @@ pub async fn find_keyframe(path: &Path, target: f64) -> Result<f64> {
             "frame=pkt_pts_time",
             "-of",
             "json",
-            "-read_intervals",
-            &interval,
         ])
         .arg(path)
         .output()
@@ -177,28 +430,80 @@ pub async fn find_keyframe(path: &Path, target: f64) -> Result<f64> {
     let output_str = String::from_utf8_lossy(&output.stdout);
     let result: FFProbeFrameOutput = serde_json::from_str(&output_str)?;
 
-    if let Some(frames) = result.frames {
-        // Find last keyframe <= target
-        // Add a small epsilon 0.1 to include target if it is exactly a keyframe
-        let mut candidate = 0.0;
-        let mut found = false;
-
-        for frame in frames {
-            let ts = frame.pkt_pts_time.parse::<f64>().unwrap_or(-1.0);
-            if ts >= 0.0 && ts <= target + 0.1 {
-                candidate = ts;
-                found = true;
-            }
+    let mut timestamps: Vec<f64> = result
+        .frames
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|f| f.pkt_pts_time.parse::<f64>().ok())
+        .filter(|ts| *ts >= 0.0)
+        .collect();
+    timestamps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let index = KeyframeIndex {
+        mtime,
+        size,
+        timestamps,
+    };
+
+    if let Ok(json) = serde_json::to_vec(&index)
+        && let Err(e) = tokio::fs::write(KeyframeIndex::sidecar_path(path), json).await
+    {
+        eprintln!("[keyframe] Failed to write sidecar index: {}", e);
+    }
+
+    Ok(index)
+}
+
+/// In-memory front of the sidecar-backed keyframe index, shared off
+/// `streaming::manager::TranscodeManager` so repeated seeks into the same
+/// file stay a binary search instead of re-running `ffprobe` every time.
+#[derive(Default)]
+pub struct KeyframeCache {
+    indexes: Mutex<HashMap<PathBuf, Arc<KeyframeIndex>>>,
+}
+
+impl KeyframeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn get_or_build(&self, path: &Path) -> Result<Arc<KeyframeIndex>> {
+        let (mtime, size) = file_stat(path)?;
+
+        if let Some(index) = self.indexes.lock().await.get(path)
+            && index.matches(mtime, size)
+        {
+            return Ok(index.clone());
         }
 
-        if found {
-            println!("[keyframe] Found keyframe at {}", candidate);
-            return Ok(candidate);
+        if let Ok(bytes) = tokio::fs::read(KeyframeIndex::sidecar_path(path)).await
+            && let Ok(index) = serde_json::from_slice::<KeyframeIndex>(&bytes)
+            && index.matches(mtime, size)
+        {
+            let index = Arc::new(index);
+            self.indexes
+                .lock()
+                .await
+                .insert(path.to_path_buf(), index.clone());
+            return Ok(index);
         }
+
+        let index = Arc::new(build_keyframe_index(path, mtime, size).await?);
+        self.indexes
+            .lock()
+            .await
+            .insert(path.to_path_buf(), index.clone());
+        Ok(index)
+    }
+}
+
+pub async fn find_keyframe(cache: &KeyframeCache, path: &Path, target: f64) -> Result<f64> {
+    if target <= 0.0 {
+        return Ok(0.0);
     }
 
-    println!("[keyframe] No keyframe found, defaulting to target.");
-    Ok(target)
+    let index = cache.get_or_build(path).await?;
+    Ok(index.lookup(target))
 }
 
 // --- Process Stream Wrapper ---
@@ -231,6 +536,14 @@ impl Stream for ProcessStream {
 struct FFProbeOutput {
     streams: Option<Vec<FFProbeStream>>,
     format: Option<FFProbeFormat>,
+    chapters: Option<Vec<FFProbeChapter>>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeChapter {
+    start_time: Option<String>,
+    end_time: Option<String>,
+    tags: Option<FFProbeTags>,
 }
 
 #[derive(Deserialize)]
@@ -254,30 +567,133 @@ struct FFProbeTags {
     language: Option<String>,
     title: Option<String>,
     label: Option<String>,
+    creation_time: Option<String>,
 }
 
-pub async fn probe_metadata(path: &Path) -> Result<MovieMetadata> {
-    let output = tokio::process::Command::new("ffprobe")
-        .args(&[
-            "-v",
-            "quiet",
-            "-print_format",
-            "json",
-            "-show_streams",
-            "-show_format",
-        ])
-        .arg(path)
-        .output()
-        .await
-        .context("Failed to run ffprobe")?;
+/// How much of a `Stream` input `probe_metadata` buffers in memory before
+/// handing it to `ffprobe` over stdin. A live pipe can only be read forward
+/// once, and ffprobe needs the container header to report streams/duration,
+/// so we can't just point it at the whole thing the way we can a seekable
+/// file -- this is the tradeoff the request calls out: buffer a bounded
+/// probe window instead.
+const PROBE_WINDOW_BYTES: usize = 4 * 1024 * 1024;
+
+/// Replays a buffered prefix ahead of the rest of an `AsyncRead`, so a
+/// `Stream` input consumed by `probe_metadata`'s probe window can still be
+/// handed whole to a later `spawn_ffmpeg`/`extract_subtitle` call.
+struct ReplayReader<R> {
+    prefix: Vec<u8>,
+    pos: usize,
+    rest: R,
+}
 
-    if !output.status.success() {
-        return Err(anyhow::anyhow!("ffprobe failed"));
+impl<R: AsyncRead + Unpin> AsyncRead for ReplayReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        if self.pos < self.prefix.len() {
+            let remaining = &self.prefix[self.pos..];
+            let n = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..n]);
+            self.pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut self.rest).poll_read(cx, buf)
     }
+}
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
+/// Runs ffprobe's `-show_streams -show_format` over `input`, returning the
+/// raw JSON and the (possibly reconstructed) `InputSource` a caller should
+/// use for any subsequent read of the same media.
+async fn run_ffprobe_metadata(input: InputSource) -> Result<(String, InputSource)> {
+    match input {
+        InputSource::File(path) => {
+            let output = tokio::process::Command::new("ffprobe")
+                .args(&[
+                    "-v",
+                    "quiet",
+                    "-print_format",
+                    "json",
+                    "-show_streams",
+                    "-show_format",
+                    "-show_chapters",
+                ])
+                .arg(&path)
+                .output()
+                .await
+                .context("Failed to run ffprobe")?;
+
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("ffprobe failed"));
+            }
+
+            Ok((
+                String::from_utf8_lossy(&output.stdout).to_string(),
+                InputSource::File(path),
+            ))
+        }
+        InputSource::Stream(mut reader) => {
+            let mut prefix = vec![0u8; PROBE_WINDOW_BYTES];
+            let mut filled = 0;
+            while filled < prefix.len() {
+                let n = reader
+                    .read(&mut prefix[filled..])
+                    .await
+                    .context("Failed to buffer probe window")?;
+                if n == 0 {
+                    break;
+                }
+                filled += n;
+            }
+            prefix.truncate(filled);
+
+            let mut command = tokio::process::Command::new("ffprobe");
+            command
+                .args(&[
+                    "-v",
+                    "quiet",
+                    "-print_format",
+                    "json",
+                    "-show_streams",
+                    "-show_format",
+                    "-show_chapters",
+                    "-i",
+                    "pipe:0",
+                ])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            let mut child = command.spawn().context("Failed to spawn ffprobe")?;
+            let mut stdin = child.stdin.take().expect("stdin was piped");
+            let probe_bytes = prefix.clone();
+            let write_task = tokio::spawn(async move { stdin.write_all(&probe_bytes).await });
+            let output = child
+                .wait_with_output()
+                .await
+                .context("Failed to run ffprobe")?;
+            let _ = write_task.await;
+
+            if !output.status.success() {
+                return Err(anyhow::anyhow!("ffprobe failed"));
+            }
+
+            Ok((
+                String::from_utf8_lossy(&output.stdout).to_string(),
+                InputSource::Stream(Box::new(ReplayReader {
+                    prefix,
+                    pos: 0,
+                    rest: reader,
+                })),
+            ))
+        }
+    }
+}
+
+fn parse_metadata_json(output_str: &str) -> Result<MovieMetadata> {
     let probe: FFProbeOutput =
-        serde_json::from_str(&output_str).context("Failed to parse ffprobe output")?;
+        serde_json::from_str(output_str).context("Failed to parse ffprobe output")?;
 
     let duration = probe
         .format
@@ -347,6 +763,31 @@ pub async fn probe_metadata(path: &Path) -> Result<MovieMetadata> {
         }
     }
 
+    let creation_time = probe
+        .format
+        .as_ref()
+        .and_then(|f| f.tags.as_ref())
+        .and_then(|t| t.creation_time.as_ref())
+        .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&chrono::Utc));
+
+    let chapters = probe
+        .chapters
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| crate::models::Chapter {
+            start: c
+                .start_time
+                .and_then(|s| s.parse::<f64>().ok())
+                .unwrap_or(0.0),
+            end: c
+                .end_time
+                .and_then(|s| s.parse::<f64>().ok())
+                .unwrap_or(0.0),
+            title: c.tags.and_then(|t| t.title),
+        })
+        .collect();
+
     let title = probe.format.and_then(|f| f.tags).and_then(|t| t.title);
 
     Ok(MovieMetadata {
@@ -355,5 +796,86 @@ pub async fn probe_metadata(path: &Path) -> Result<MovieMetadata> {
         title,
         audio_tracks,
         subtitle_tracks,
+        chapters,
+        creation_time,
     })
 }
+
+/// Probes `input` for duration/codec/track info. Returns the `InputSource`
+/// back alongside the metadata since a `Stream` input is partially consumed
+/// by the probe itself (see `PROBE_WINDOW_BYTES`) -- callers that need to
+/// feed the same media to `spawn_ffmpeg`/`extract_subtitle` afterward should
+/// use the returned source, not the one they passed in.
+pub async fn probe_metadata(input: InputSource) -> Result<(MovieMetadata, InputSource)> {
+    let (output_str, input) = run_ffprobe_metadata(input).await?;
+    let metadata = parse_metadata_json(&output_str)?;
+    Ok((metadata, input))
+}
+
+// --- Stream Inventory ---
+//
+// `probe_metadata` re-indexes audio/subtitle tracks per codec_type (matching
+// the `0:a:N`/`0:s:N` stream specifiers `spawn_ffmpeg`/`extract_subtitle`
+// use), which is convenient for playback but throws away the real ffprobe
+// stream index and disposition. `probe_streams` exposes the raw inventory
+// instead, so clients can show language/title/default before picking a track.
+
+#[derive(Deserialize)]
+struct FFProbeDisposition {
+    #[serde(default)]
+    default: u8,
+}
+
+#[derive(Deserialize)]
+struct FFProbeStreamFull {
+    index: usize,
+    codec_name: Option<String>,
+    codec_type: String,
+    channels: Option<usize>,
+    tags: Option<FFProbeTags>,
+    disposition: Option<FFProbeDisposition>,
+}
+
+#[derive(Deserialize)]
+struct FFProbeStreamsOutput {
+    streams: Option<Vec<FFProbeStreamFull>>,
+}
+
+pub async fn probe_streams(path: &Path) -> Result<Vec<StreamInfo>> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(&[
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-show_entries",
+            "stream=index,codec_name,codec_type,channels:stream_tags=language,title:disposition=default",
+        ])
+        .arg(path)
+        .output()
+        .await
+        .context("Failed to run ffprobe")?;
+
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("ffprobe failed"));
+    }
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let probe: FFProbeStreamsOutput =
+        serde_json::from_str(&output_str).context("Failed to parse ffprobe output")?;
+
+    Ok(probe
+        .streams
+        .unwrap_or_default()
+        .into_iter()
+        .map(|s| StreamInfo {
+            index: s.index,
+            codec_type: s.codec_type,
+            codec: s.codec_name.unwrap_or_else(|| "unknown".to_string()),
+            channels: s.channels,
+            language: s.tags.as_ref().and_then(|t| t.language.clone()),
+            title: s.tags.and_then(|t| t.title.or(t.label)),
+            is_default: s.disposition.map(|d| d.default == 1).unwrap_or(false),
+        })
+        .collect())
+}
@@ -0,0 +1,112 @@
+//! Data-driven transcode profile selection. `spawn_ffmpeg` used to hardcode
+//! `-c:v copy`, which breaks outright for a client that can't decode the
+//! source codec at all (e.g. a browser handed raw HEVC, VP9, or AV1). A
+//! `TranscodeProfile` rule decides, from the probed source codec and the
+//! codecs a client says it supports, whether to remux (`copy`) or transcode,
+//! and if transcoding which target codec/bitrate/scale/preset to use --
+//! adding a new device class is adding a rule to `PROFILES`, not touching
+//! `spawn_ffmpeg`.
+
+/// One fallback rule. `match_codecs` gates on the *source* codec (empty
+/// means "any source codec"); `copy` rules additionally require the client
+/// to list `target_codec` as supported, since copying only works if the
+/// client can already decode what's in the file.
+pub struct TranscodeProfile {
+    pub match_codecs: &'static [&'static str],
+    pub target_codec: &'static str,
+    pub copy: bool,
+    pub crf: Option<u32>,
+    pub max_bitrate_kbps: Option<u32>,
+    pub scale_height: Option<u32>,
+    pub preset: Option<&'static str>,
+}
+
+impl TranscodeProfile {
+    /// The ffmpeg `-c:v` encoder name for `target_codec`. Only meaningful
+    /// when `copy` is `false`.
+    pub fn encoder_name(&self) -> &'static str {
+        match self.target_codec {
+            "h264" => "libx264",
+            "hevc" => "libx265",
+            "vp9" => "libvpx-vp9",
+            "av1" => "libaom-av1",
+            other => other,
+        }
+    }
+}
+
+/// Checked top-to-bottom; the first rule whose `match_codecs` covers the
+/// source codec wins. Copy rules further require the client to support the
+/// source codec; the final rule has no such requirement and always matches,
+/// so the list always resolves to an H.264 transcode when nothing else fits.
+pub const PROFILES: &[TranscodeProfile] = &[
+    TranscodeProfile {
+        match_codecs: &["h264"],
+        target_codec: "h264",
+        copy: true,
+        crf: None,
+        max_bitrate_kbps: None,
+        scale_height: None,
+        preset: None,
+    },
+    TranscodeProfile {
+        match_codecs: &["hevc"],
+        target_codec: "hevc",
+        copy: true,
+        crf: None,
+        max_bitrate_kbps: None,
+        scale_height: None,
+        preset: None,
+    },
+    TranscodeProfile {
+        match_codecs: &["vp9"],
+        target_codec: "vp9",
+        copy: true,
+        crf: None,
+        max_bitrate_kbps: None,
+        scale_height: None,
+        preset: None,
+    },
+    TranscodeProfile {
+        match_codecs: &["av1"],
+        target_codec: "av1",
+        copy: true,
+        crf: None,
+        max_bitrate_kbps: None,
+        scale_height: None,
+        preset: None,
+    },
+    // Universal fallback: transcode to H.264, which every client we serve
+    // claims to decode, capped at 1080p to keep encode time reasonable.
+    TranscodeProfile {
+        match_codecs: &[],
+        target_codec: "h264",
+        copy: false,
+        crf: Some(23),
+        max_bitrate_kbps: Some(8000),
+        scale_height: Some(1080),
+        preset: Some("veryfast"),
+    },
+];
+
+/// Picks the profile to use for a source encoded as `source_codec`, given
+/// the lowercase codec names a client reports supporting (from a
+/// `?supported_codecs=h264,hevc`-style query param; an empty list means the
+/// client didn't say, so only the universal fallback can match).
+pub fn resolve_profile(
+    source_codec: &str,
+    supported_codecs: &[String],
+) -> &'static TranscodeProfile {
+    PROFILES
+        .iter()
+        .find(|profile| {
+            let source_matches =
+                profile.match_codecs.is_empty() || profile.match_codecs.contains(&source_codec);
+            let client_can_play = !profile.copy
+                || supported_codecs
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(profile.target_codec));
+            source_matches && client_can_play
+        })
+        .unwrap_or_else(|| PROFILES.last().expect("PROFILES is never empty"))
+}
@@ -0,0 +1,57 @@
+//! Binds a `Router` plaintext or over rustls, depending on `AppConfig`'s
+//! optional `tls_cert_path`/`tls_key_path`. `main.rs` binds its combined
+//! router through `serve`, passing the `models::AppState` config.
+//!
+//! Choosing between a `webpki-roots`/`native-roots` trust store (as
+//! external HTTP client crates commonly feature-gate) doesn't apply on the
+//! listening side here -- that choice governs which root CAs a *client*
+//! trusts when verifying a server's cert, not which cert a server presents.
+//! It would become relevant if this crate grows an outbound HTTPS client
+//! (e.g. talking to TMDB over a pinned trust store) behind its own
+//! `tls-webpki-roots`/`tls-native-roots` Cargo features.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+
+use crate::models::AppConfig;
+
+/// Whether `config` has both halves of a TLS keypair configured.
+/// `auth::AuthState::new` consults this to force `Secure` cookies on.
+pub fn tls_active(config: &AppConfig) -> bool {
+    config.tls_cert_path.is_some() && config.tls_key_path.is_some()
+}
+
+/// Binds `router` on `config.host:config.port` -- over rustls when
+/// `tls_active(config)`, plaintext otherwise.
+pub async fn serve(router: Router, config: &AppConfig) -> Result<()> {
+    let addr: SocketAddr = format!("{}:{}", config.host, config.port)
+        .parse()
+        .context("Invalid host/port in AppConfig")?;
+
+    match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert), Some(key)) => {
+            let tls_config = RustlsConfig::from_pem_file(cert, key)
+                .await
+                .context("Failed to load TLS cert/key")?;
+            println!("Listening on {} (TLS)", addr);
+            axum_server::bind_rustls(addr, tls_config)
+                .serve(router.into_make_service())
+                .await
+                .context("TLS server error")?;
+        }
+        _ => {
+            println!("Listening on {} (plaintext)", addr);
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .context("Failed to bind listener")?;
+            axum::serve(listener, router)
+                .await
+                .context("Server error")?;
+        }
+    }
+
+    Ok(())
+}
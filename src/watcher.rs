@@ -0,0 +1,205 @@
+//! Live filesystem watching for `Scanner`. `scan_library` only walks a
+//! library once at startup; `Watcher` keeps watching each library's path
+//! afterwards so files dropped in later are picked up without a manual
+//! rescan, feeding the same `ScanTask` channel so throttling and metadata
+//! fetching stay unified between the two paths.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as NotifyWatcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, sleep};
+
+use crate::matcher::{match_filename, match_season_folder};
+use crate::metadata::read_local_metadata;
+use crate::models::{Library, LibraryType};
+use crate::scanner::{ScanTask, has_metadata};
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "webm", "m4v", "flv", "wmv"];
+
+/// Holds the live `notify` watchers so they aren't dropped (and stopped)
+/// as soon as `Watcher::start` returns.
+pub struct Watcher {
+    _watchers: Vec<RecommendedWatcher>,
+}
+
+impl Watcher {
+    /// Registers a recursive watch on every library path and starts a
+    /// background task that debounces and dispatches the resulting events
+    /// onto `tx`, the same sender `Scanner`'s worker loop reads from.
+    pub fn start(libraries: Vec<Library>, tx: mpsc::Sender<ScanTask>) -> Self {
+        let (raw_tx, raw_rx) = mpsc::channel::<(PathBuf, Library)>(256);
+        let mut watchers = Vec::new();
+
+        for library in libraries {
+            let path = library.path.clone();
+            let event_tx = raw_tx.clone();
+            let lib_for_events = library.clone();
+
+            let mut watcher = match notify::recommended_watcher(move |res: notify::Result<Event>| {
+                let event = match res {
+                    Ok(e) => e,
+                    Err(e) => {
+                        eprintln!("[watcher] Error watching {:?}: {}", lib_for_events.path, e);
+                        return;
+                    }
+                };
+                // Only create/write/remove carry paths we care about. An
+                // editor's atomic rename (temp file -> final name) shows up
+                // as a rename event whose `paths` is just the final path, so
+                // it's handled the same as a plain create.
+                if !matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                ) {
+                    return;
+                }
+                for changed_path in event.paths {
+                    let _ = event_tx.blocking_send((changed_path, lib_for_events.clone()));
+                }
+            }) {
+                Ok(w) => w,
+                Err(e) => {
+                    eprintln!("[watcher] Failed to create watcher for {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            if let Err(e) = watcher.watch(&path, RecursiveMode::Recursive) {
+                eprintln!("[watcher] Failed to watch {:?}: {}", path, e);
+                continue;
+            }
+
+            println!("[watcher] Watching library {:?} at {:?}", library.name, path);
+            watchers.push(watcher);
+        }
+
+        tokio::spawn(debounce_and_dispatch(raw_rx, tx));
+
+        Self {
+            _watchers: watchers,
+        }
+    }
+}
+
+/// Coalesces bursts of events on the same path within a 2s window, so a
+/// create followed by several writes (or a save that touches the file
+/// twice) only produces one `ScanTask`.
+async fn debounce_and_dispatch(
+    mut raw_rx: mpsc::Receiver<(PathBuf, Library)>,
+    tx: mpsc::Sender<ScanTask>,
+) {
+    const DEBOUNCE_WINDOW: Duration = Duration::from_secs(2);
+    let mut pending: HashMap<PathBuf, (Library, Instant)> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            event = raw_rx.recv() => {
+                match event {
+                    Some((path, library)) => {
+                        pending.insert(path, (library, Instant::now()));
+                    }
+                    None => break,
+                }
+            }
+            _ = sleep(Duration::from_millis(250)), if !pending.is_empty() => {
+                let settled: Vec<PathBuf> = pending
+                    .iter()
+                    .filter(|(_, (_, last_seen))| last_seen.elapsed() >= DEBOUNCE_WINDOW)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                for path in settled {
+                    if let Some((library, _)) = pending.remove(&path) {
+                        handle_settled_path(&path, &library, &tx).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Maps one settled (debounced) filesystem path onto a `ScanTask`, reusing
+/// the matcher/`has_metadata` logic `Scanner`'s own walk relies on.
+async fn handle_settled_path(path: &Path, library: &Library, tx: &mpsc::Sender<ScanTask>) {
+    if !path.is_file() {
+        return;
+    }
+    let is_video = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false);
+    if !is_video || has_metadata(path).await {
+        return;
+    }
+
+    match library.kind {
+        LibraryType::Movies => {
+            println!("[watcher] New movie detected: {:?}", path);
+            let _ = tx
+                .send(ScanTask::Movie {
+                    path: path.to_path_buf(),
+                    library_id: library.id.clone(),
+                })
+                .await;
+        }
+        LibraryType::TVShows | LibraryType::Anime => {
+            let season_from_parent = path
+                .parent()
+                .and_then(|p| p.file_name())
+                .and_then(|name| match_season_folder(&name.to_string_lossy()));
+
+            let Some(show_id) = find_show_tmdb_id(path, library).await else {
+                println!(
+                    "[watcher] Could not resolve show metadata for {:?}; skipping",
+                    path
+                );
+                return;
+            };
+
+            let file_name = path.file_name().unwrap().to_string_lossy();
+            let m = match_filename(&file_name, season_from_parent, true);
+
+            let (season_num, episode_num) = if let (Some(s), Some(e)) = (m.season, m.episode) {
+                (s, e)
+            } else if let Some(abs_ep) = m.episode_absolute {
+                (season_from_parent.unwrap_or(1), abs_ep)
+            } else {
+                println!(
+                    "[watcher] Could not determine season/episode for {:?}; skipping",
+                    path
+                );
+                return;
+            };
+
+            println!(
+                "[watcher] New episode detected: {:?} (S{:02}E{:02})",
+                path, season_num, episode_num
+            );
+            let _ = tx
+                .send(ScanTask::Episode {
+                    path: path.to_path_buf(),
+                    tmdb_id: show_id,
+                    season_num,
+                    episode_num,
+                    library_id: library.id.clone(),
+                })
+                .await;
+        }
+        // Remote libraries have no local path, so `Watcher::start`'s
+        // `watch` call fails for them and no event reaches here; `Other`
+        // libraries are watched, but their files are not queued.
+        LibraryType::Remote | LibraryType::Other => {}
+    }
+}
+
+/// Walks up from a newly-seen file to the show folder (the library's direct
+/// child directory) and reads its already-saved TMDB id.
+async fn find_show_tmdb_id(path: &Path, library: &Library) -> Option<u64> {
+    let mut dir = path.parent()?;
+    while dir.parent()? != library.path {
+        dir = dir.parent()?;
+    }
+    read_local_metadata(dir).await.map(|meta| meta.tmdb_id)
+}